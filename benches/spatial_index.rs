@@ -0,0 +1,228 @@
+//! Compares hit-testing with the spatial index of a `Layer` against scanning every point.
+//!
+//! The index is built while checking that both find the same points, so only updates are part of the measurement.
+//! Run with `cargo bench --bench spatial_index`.
+use hnote::model::{Drawpoint, Layer, Stroke};
+use hnote::tools::{DrawTool, Eraser};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CANVAS_SIZE: (f64, f64) = (1920.0, 1080.0);
+const POINTS_PER_STROKE: usize = 50;
+const QUERIES: usize = 1000;
+const PEN_SIZE: f64 = 5.0;
+
+/// Creates a layer with `strokes` short random walks of `POINTS_PER_STROKE` points.
+fn dense_layer(rng: &mut StdRng, strokes: usize) -> Layer {
+	let mut layer = Layer::new("Benchmark");
+	for _ in 0..strokes {
+		let mut position = random_position(rng);
+		let points = (0..POINTS_PER_STROKE)
+			.map(|_| {
+				position.0 += rng.gen_range(-3.0..3.0);
+				position.1 += rng.gen_range(-3.0..3.0);
+				Drawpoint::new(position, 2.0, [0.0, 0.0, 0.0, 1.0])
+			})
+			.collect();
+		layer.push_line(Stroke::new(points));
+	}
+	layer
+}
+
+fn random_position(rng: &mut StdRng) -> (f64, f64) {
+	(
+		rng.gen_range(0.0..CANVAS_SIZE.0),
+		rng.gen_range(0.0..CANVAS_SIZE.1),
+	)
+}
+
+fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+	((from.0 - to.0).powf(2.0) + (from.1 - to.1).powf(2.0)).sqrt()
+}
+
+/// Points closer than `PEN_SIZE` to `position`, found by scanning every point.
+fn scan_near(layer: &Layer, position: (f64, f64)) -> usize {
+	layer
+		.lines()
+		.iter()
+		.flat_map(|line| line.points.iter())
+		.filter(|point| distance(point.position, position) < PEN_SIZE)
+		.count()
+}
+
+/// Points closer than `PEN_SIZE` to `position`, found with the spatial index.
+fn index_near(layer: &mut Layer, position: (f64, f64)) -> usize {
+	layer
+		.lines_near(position, PEN_SIZE)
+		.into_iter()
+		.flat_map(|i| layer.lines()[i].points.iter())
+		.filter(|point| distance(point.position, position) < PEN_SIZE)
+		.count()
+}
+
+/// Closest point to `position`, found by scanning every point.
+fn scan_closest(layer: &Layer, position: (f64, f64)) -> Option<(f64, f64)> {
+	layer
+		.lines()
+		.iter()
+		.flat_map(|line| line.points.iter())
+		.map(|point| point.position)
+		.min_by(|a, b| {
+			distance(*a, position)
+				.partial_cmp(&distance(*b, position))
+				.unwrap()
+		})
+}
+
+/// Lines with points in a 100 x 100 square at `position`, found by scanning every point.
+fn scan_rectangle(layer: &Layer, position: (f64, f64)) -> usize {
+	layer
+		.lines()
+		.iter()
+		.filter(|line| {
+			line.points
+				.iter()
+				.any(|point| in_square(point.position, position))
+		})
+		.count()
+}
+
+/// Lines with points in a 100 x 100 square at `position`, found with the spatial index.
+fn index_rectangle(layer: &mut Layer, position: (f64, f64)) -> usize {
+	let rectangle = [
+		position.0,
+		position.1,
+		position.0 + 100.0,
+		position.1 + 100.0,
+	];
+	layer
+		.lines_in_rectangle(rectangle)
+		.into_iter()
+		.filter(|i| {
+			layer.lines()[*i]
+				.points
+				.iter()
+				.any(|point| in_square(point.position, position))
+		})
+		.count()
+}
+
+fn in_square(point: (f64, f64), position: (f64, f64)) -> bool {
+	point.0 > position.0
+		&& point.1 > position.1
+		&& point.0 < position.0 + 100.0
+		&& point.1 < position.1 + 100.0
+}
+
+/// Splits the `lines` at the points closer than `PEN_SIZE` to `position` like `Eraser`, scanning every point.
+fn scan_erase(lines: &mut Vec<Stroke>, position: (f64, f64)) {
+	let mut removal_queue = Vec::new();
+	for (i, line) in lines.iter().enumerate() {
+		for (j, point) in line.points.iter().enumerate() {
+			if distance(point.position, position) < PEN_SIZE {
+				removal_queue.push((i, j));
+			}
+		}
+	}
+	let mut new_element_count = 0;
+	for (i, j) in removal_queue {
+		let i = i + new_element_count;
+		if i < lines.len() && j < lines[i].points.len() {
+			let line = lines[i].split_off(j);
+			lines.insert(i + 1, line);
+			new_element_count += 1;
+		}
+	}
+}
+
+/// Creates `QUERIES` positions that follow each other in small steps, like pointer motion events.
+fn random_walk(rng: &mut StdRng) -> Vec<(f64, f64)> {
+	let mut position = random_position(rng);
+	(0..QUERIES)
+		.map(|_| {
+			position.0 = (position.0 + rng.gen_range(-4.0..4.0)).rem_euclid(CANVAS_SIZE.0);
+			position.1 = (position.1 + rng.gen_range(-4.0..4.0)).rem_euclid(CANVAS_SIZE.1);
+			position
+		})
+		.collect()
+}
+
+/// Runs `query` for `QUERIES` random positions and returns the average time per query.
+fn measure(positions: &[(f64, f64)], mut query: impl FnMut((f64, f64))) -> Duration {
+	let start = Instant::now();
+	for position in positions {
+		query(black_box(*position));
+	}
+	start.elapsed() / positions.len() as u32
+}
+
+fn report(name: &str, points: usize, scan: Duration, index: Duration) {
+	println!(
+		"{:<22} {:>7} points   scan {:>10.1?}   index {:>10.1?}   speedup {:>6.1}x",
+		name,
+		points,
+		scan,
+		index,
+		scan.as_secs_f64() / index.as_secs_f64()
+	);
+}
+
+fn main() {
+	let mut rng = StdRng::seed_from_u64(0);
+	for strokes in [200, 1000, 4000].iter() {
+		let layer = dense_layer(&mut rng, *strokes);
+		let points = strokes * POINTS_PER_STROKE;
+		let positions: Vec<(f64, f64)> = (0..QUERIES).map(|_| random_position(&mut rng)).collect();
+		let mut indexed = layer.clone();
+		for position in positions.iter() {
+			assert_eq!(
+				index_near(&mut indexed, *position),
+				scan_near(&layer, *position)
+			);
+			let closest = indexed
+				.closest_line_position(*position)
+				.map(|(_, point)| point);
+			assert_eq!(
+				closest.map(|point| distance(point, *position)),
+				scan_closest(&layer, *position).map(|point| distance(point, *position))
+			);
+		}
+
+		let scan = measure(&positions, |position| {
+			black_box(scan_near(&layer, position));
+		});
+		let index = measure(&positions, |position| {
+			black_box(index_near(&mut indexed, position));
+		});
+		report("Points near pointer", points, scan, index);
+
+		let scan = measure(&positions, |position| {
+			black_box(scan_closest(&layer, position));
+		});
+		let index = measure(&positions, |position| {
+			black_box(indexed.closest_line_position(position));
+		});
+		report("Closest point", points, scan, index);
+
+		let scan = measure(&positions, |position| {
+			black_box(scan_rectangle(&layer, position));
+		});
+		let index = measure(&positions, |position| {
+			black_box(index_rectangle(&mut indexed, position));
+		});
+		report("Lines in rectangle", points, scan, index);
+
+		// Erases along a continuous path, so strokes are split and the index is updated between the queries.
+		let path = random_walk(&mut rng);
+		let mut scanned = layer.lines().to_vec();
+		let scan = measure(&path, |position| scan_erase(&mut scanned, position));
+		let mut eraser = Eraser::default();
+		let index = measure(&path, |position| {
+			eraser.manipulate(&mut indexed, &[], position, PEN_SIZE, true, [0.0; 4]);
+		});
+		assert_eq!(scanned.len(), indexed.lines().len());
+		report("Eraser motion", points, scan, index);
+	}
+}
@@ -0,0 +1,195 @@
+use crate::document::Document;
+use crate::error::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Name of the archive entry that contains the serialized `Document`.
+const DOCUMENT_ENTRY: &str = "document.json";
+
+/// Directory of the archive entries that contain image data, named by their content hash.
+const IMAGE_DIRECTORY: &str = "images/";
+
+/// Every zip archive starts with a local file header signature.
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// Image data keyed by its content hash.
+pub type ImageData = HashMap<String, Rc<Vec<u8>>>;
+
+/// Calculates the hex encoded SHA-256 hash that identifies image `data`.
+pub fn content_hash(data: &[u8]) -> String {
+	format!("{:x}", Sha256::digest(data))
+}
+
+/// Writes `document` as a zip bundle.
+///
+/// The bundle contains the document JSON and the data of every referenced image exactly once.
+pub fn write_bundle<W: Write + Seek>(writer: W, document: &Document) -> Result<()> {
+	let mut zip = ZipWriter::new(writer);
+	zip.start_file(DOCUMENT_ENTRY, FileOptions::default())?;
+	zip.write_all(document.to_json()?.as_bytes())?;
+	let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+	for (hash, data) in document.image_data() {
+		zip.start_file(format!("{}{}", IMAGE_DIRECTORY, hash), stored)?;
+		zip.write_all(&data)?;
+	}
+	zip.finish()?;
+	Ok(())
+}
+
+/// Writes `document` as a zip bundle to `path` without ever leaving a truncated file behind.
+///
+/// The bundle is written to a temporary file in the same directory, synced to disk and renamed over `path`.
+/// Previous versions of `path` are kept as `path.bak.1` (newest) up to `path.bak.<backup_count>` (oldest).
+pub fn save_bundle(path: &Path, document: &Document, backup_count: usize) -> Result<()> {
+	let temporary_path = suffixed_path(path, ".tmp");
+	let written = File::create(&temporary_path)
+		.map_err(Error::from)
+		.and_then(|mut file| {
+			write_bundle(&mut file, document)?;
+			file.sync_all()?;
+			Ok(())
+		});
+	if let Err(error) = written {
+		let _ = fs::remove_file(&temporary_path);
+		return Err(error);
+	}
+	if backup_count > 0 && path.exists() {
+		rotate_backups(path, backup_count)?;
+	}
+	fs::rename(&temporary_path, path)?;
+	if let Some(directory) = path.parent().and_then(|parent| File::open(parent).ok()) {
+		let _ = directory.sync_all();
+	}
+	Ok(())
+}
+
+/// Shifts every backup of `path` one position back and turns the current `path` into the newest backup.
+///
+/// The oldest backup is dropped once `backup_count` backups exist.
+fn rotate_backups(path: &Path, backup_count: usize) -> Result<()> {
+	let backup_path = |n: usize| suffixed_path(path, &format!(".bak.{}", n));
+	for n in (1..backup_count).rev() {
+		if backup_path(n).exists() {
+			fs::rename(backup_path(n), backup_path(n + 1))?;
+		}
+	}
+	let newest_backup = backup_path(1);
+	let _ = fs::remove_file(&newest_backup);
+	if fs::hard_link(path, &newest_backup).is_err() {
+		fs::copy(path, &newest_backup)?;
+	}
+	Ok(())
+}
+
+/// Appends `suffix` to the file name of `path`.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = OsString::from(path);
+	path.push(suffix);
+	PathBuf::from(path)
+}
+
+/// Reads a `Document` from `serialized`, including the data of all its images.
+///
+/// Besides zip bundles, plain JSON documents written by older versions are accepted.
+pub fn read_bundle(serialized: &[u8]) -> Result<Document> {
+	if !serialized.starts_with(ZIP_SIGNATURE) {
+		let serialized = std::str::from_utf8(serialized)
+			.map_err(|_| Error::Format("Not an .hnote document.".to_string()))?;
+		return Document::from_json(serialized, ImageData::new());
+	}
+	let mut archive = ZipArchive::new(Cursor::new(serialized))?;
+	let mut document = String::new();
+	archive
+		.by_name(DOCUMENT_ENTRY)?
+		.read_to_string(&mut document)?;
+	let mut image_data = ImageData::new();
+	for i in 0..archive.len() {
+		let mut file = archive.by_index(i)?;
+		if let Some(hash) = file.name().strip_prefix(IMAGE_DIRECTORY) {
+			let hash = hash.to_string();
+			let mut data = Vec::new();
+			file.read_to_end(&mut data)?;
+			image_data.insert(hash, Rc::new(data));
+		}
+	}
+	Document::from_json(&document, image_data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::{new_element_id, Drawpoint, Image, Page, Stroke};
+
+	fn document() -> Document {
+		let mut page = Page::new();
+		let layer = &mut page.layers[0];
+		layer.push_line(Stroke::new(vec![Drawpoint::new(
+			(1.0, 2.0),
+			3.0,
+			[1.0, 0.0, 0.0, 0.5],
+		)]));
+		for position in [(10.0, 20.0), (30.0, 40.0)].iter() {
+			layer.images.push(Image::new(
+				PathBuf::from("image.png"),
+				b"image data".to_vec(),
+				*position,
+			));
+		}
+		Document::new(vec![page, Page::new()])
+	}
+
+	#[test]
+	fn bundle_round_trips_with_images_stored_once() {
+		let document = document();
+		let mut serialized = Cursor::new(Vec::new());
+		write_bundle(&mut serialized, &document).unwrap();
+		let serialized = serialized.into_inner();
+
+		let archive = ZipArchive::new(Cursor::new(&serialized)).unwrap();
+		assert_eq!(archive.len(), 2);
+		let read = read_bundle(&serialized).unwrap();
+		assert_eq!(read.pages.len(), 2);
+		let (layer, read_layer) = (&document.pages[0].layers[0], &read.pages[0].layers[0]);
+		assert_eq!(read_layer.lines(), layer.lines());
+		assert_eq!(read_layer.images.len(), 2);
+		for (image, read_image) in layer.images.iter().zip(read_layer.images.iter()) {
+			assert_eq!(read_image.id, image.id);
+			assert_eq!(read_image.position, image.position);
+			assert_eq!(read_image.data, image.data);
+		}
+	}
+
+	#[test]
+	fn plain_json_documents_are_read() {
+		let document = document();
+		let json = Document::new(document.pages[1..].to_vec())
+			.to_json()
+			.unwrap();
+		assert_eq!(read_bundle(json.as_bytes()).unwrap().pages.len(), 1);
+		assert!(read_bundle(b"not a document").is_err());
+	}
+
+	#[test]
+	fn saving_rotates_backups() {
+		let directory = std::env::temp_dir().join(format!("hnote-test-{}", new_element_id()));
+		fs::create_dir(&directory).unwrap();
+		let path = directory.join("notebook.hnote");
+		for pages in 1..=4 {
+			save_bundle(&path, &Document::new(vec![Page::new(); pages]), 2).unwrap();
+		}
+		let pages = |path: &Path| read_bundle(&fs::read(path).unwrap()).unwrap().pages.len();
+		assert_eq!(pages(&path), 4);
+		assert_eq!(pages(&suffixed_path(&path, ".bak.1")), 3);
+		assert_eq!(pages(&suffixed_path(&path, ".bak.2")), 2);
+		assert!(!suffixed_path(&path, ".bak.3").exists());
+		assert!(!suffixed_path(&path, ".tmp").exists());
+		fs::remove_dir_all(&directory).unwrap();
+	}
+}
@@ -0,0 +1,639 @@
+use crate::bundle::{content_hash, ImageData};
+use crate::error::{Error, Result};
+use crate::history::History;
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::rc::Rc;
+
+/// Identifies a JSON object as an `.hnote` document.
+pub const MAGIC: &str = "hnote";
+
+/// Version that is written on every save.
+///
+/// Has to be increased together with a new entry in `MIGRATIONS` whenever `Page`, `Layer`, `Stroke`, `Drawpoint`, `Image` or `History` change.
+pub const CURRENT_VERSION: u32 = 8;
+
+/// Upgrades a document to the next version, content that has to be left out is reported in the warnings.
+type Migration = fn(Value, &mut ImageData, &mut Vec<String>) -> Result<Value>;
+
+/// Upgrades a document from the version at its index to the next version.
+///
+/// Version 0 is the bare `Vec<Page>` JSON array written before the `Document` container existed.
+/// Migrations that need image data which is not part of the document put it into the passed `ImageData`.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [
+	migrate_0_to_1,
+	migrate_1_to_2,
+	migrate_2_to_3,
+	migrate_3_to_4,
+	migrate_4_to_5,
+	migrate_5_to_6,
+	migrate_6_to_7,
+	migrate_7_to_8,
+];
+
+/// Serializable top level container of an `.hnote` file.
+///
+/// `history` optionally keeps the undo history of `pages` across sessions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Document {
+	pub magic: String,
+	pub version: u32,
+	pub pages: Vec<Page>,
+	#[serde(default)]
+	pub history: Option<History>,
+	/// Problems that were worked around while reading the document, like image files of old documents that could not be read.
+	#[serde(skip)]
+	pub warnings: Vec<String>,
+}
+
+impl Document {
+	pub fn new(pages: Vec<Page>) -> Self {
+		Self {
+			magic: MAGIC.to_string(),
+			version: CURRENT_VERSION,
+			pages,
+			history: None,
+			warnings: Vec::new(),
+		}
+	}
+
+	/// Stores `history` in `self`.
+	pub fn with_history(mut self, history: History) -> Self {
+		self.history = Some(history);
+		self
+	}
+
+	/// Serializes `self` as the newest document version.
+	pub fn to_json(&self) -> Result<String> {
+		Ok(serde_json::to_string(self)?)
+	}
+
+	/// Deserializes a document of any known version.
+	///
+	/// Older documents are upgraded step by step with `MIGRATIONS` before being deserialized.
+	/// Afterwards every image gets its data from `image_data`.
+	/// Content that the migrations had to leave out is listed in `warnings`.
+	pub fn from_json(serialized: &str, mut image_data: ImageData) -> Result<Self> {
+		let mut value: Value = serde_json::from_str(serialized)?;
+		let version = Self::detect_version(&value)?;
+		if version > CURRENT_VERSION {
+			return Err(Error::Format(format!(
+				"Document version {} is newer than the supported version {}.",
+				version, CURRENT_VERSION
+			)));
+		}
+		let mut warnings = Vec::new();
+		for migration in MIGRATIONS[version as usize..].iter() {
+			value = migration(value, &mut image_data, &mut warnings)?;
+		}
+		let mut document: Self = serde_json::from_value(value)?;
+		if document.pages.is_empty() {
+			return Err(Error::Format("The document has no pages.".to_string()));
+		}
+		if document.pages.iter().any(|page| page.layers.is_empty()) {
+			return Err(Error::Format("A page has no layers.".to_string()));
+		}
+		document.attach_image_data(&image_data)?;
+		document.warnings = warnings;
+		Ok(document)
+	}
+
+	/// Collects the data of all images on all pages and in the history, deduplicated by content hash.
+	pub fn image_data(&self) -> ImageData {
+		let mut image_data = ImageData::new();
+		let history_images = self.history.iter().flat_map(History::images);
+		for image in self
+			.pages
+			.iter()
+			.flat_map(Page::images)
+			.chain(history_images)
+		{
+			image_data
+				.entry(image.hash.clone())
+				.or_insert_with(|| Rc::clone(&image.data));
+		}
+		image_data
+	}
+
+	/// Sets the data of every image on all pages and in the history by its content hash.
+	fn attach_image_data(&mut self, image_data: &ImageData) -> Result<()> {
+		let history_images = self.history.iter_mut().flat_map(History::images_mut);
+		for image in self
+			.pages
+			.iter_mut()
+			.flat_map(Page::images_mut)
+			.chain(history_images)
+		{
+			image.data = image_data
+				.get(&image.hash)
+				.cloned()
+				.ok_or_else(|| Error::Format(format!("Image data {} is missing.", image.hash)))?;
+		}
+		Ok(())
+	}
+
+	/// Reads the document version from the `magic` and `version` fields of `value`.
+	fn detect_version(value: &Value) -> Result<u32> {
+		match value {
+			Value::Array(_) => Ok(0),
+			Value::Object(object) if object.get("magic") == Some(&json!(MAGIC)) => object
+				.get("version")
+				.and_then(Value::as_u64)
+				.map(|version| version as u32)
+				.ok_or_else(|| Error::Format("Document version is missing.".to_string())),
+			_ => Err(Error::Format("Not an .hnote document.".to_string())),
+		}
+	}
+}
+
+/// Wraps the bare page array in a `Document` container.
+fn migrate_0_to_1(
+	value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	Ok(json!({
+		"magic": MAGIC,
+		"version": 1,
+		"pages": value,
+	}))
+}
+
+/// Embeds the image files that were only referenced by their path.
+///
+/// Each image gets the content hash of its file, the file data is put into `image_data`.
+/// Images whose file can not be read are left out and reported in `warnings`.
+fn migrate_1_to_2(
+	mut value: Value,
+	image_data: &mut ImageData,
+	warnings: &mut Vec<String>,
+) -> Result<Value> {
+	let pages = value.get_mut("pages").and_then(Value::as_array_mut);
+	for page in pages.into_iter().flatten() {
+		if let Some(images) = page.get_mut("images").and_then(Value::as_array_mut) {
+			let mut readable_images = Vec::with_capacity(images.len());
+			for mut image in images.drain(..) {
+				let path = image
+					.get("path")
+					.and_then(Value::as_str)
+					.ok_or_else(|| Error::Format("Image path is missing.".to_string()))?
+					.to_string();
+				let data = match std::fs::read(&path) {
+					Ok(data) => data,
+					Err(error) => {
+						warnings.push(format!("Image {} was left out: {}", path, error));
+						continue;
+					}
+				};
+				let hash = content_hash(&data);
+				image_data
+					.entry(hash.clone())
+					.or_insert_with(|| Rc::new(data));
+				image["hash"] = json!(hash);
+				readable_images.push(image);
+			}
+			*images = readable_images;
+		}
+	}
+	value["version"] = json!(2);
+	Ok(value)
+}
+
+/// Adds the empty undo `history`.
+fn migrate_2_to_3(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	value["history"] = Value::Null;
+	value["version"] = json!(3);
+	Ok(value)
+}
+
+/// Turns the undo and redo stacks of the `history` into a single branch of an undo tree.
+///
+/// The redone operations follow the done operations, their timestamps are unknown.
+fn migrate_3_to_4(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	if let Some(history) = value
+		.get_mut("history")
+		.filter(|history| !history.is_null())
+	{
+		let stack = |name: &str| history.get(name).and_then(Value::as_array).cloned();
+		let done = stack("done").unwrap_or_default();
+		let undone = stack("undone").unwrap_or_default();
+		let current = done.len().checked_sub(1);
+		let count = done.len() + undone.len();
+		let mut entries = Map::new();
+		for (id, mut entry) in done.into_iter().chain(undone.into_iter().rev()).enumerate() {
+			entry["timestamp"] = json!(0);
+			entry["parent"] = json!(id.checked_sub(1));
+			entry["children"] = json!((id + 1..count).take(1).collect::<Vec<_>>());
+			entries.insert(id.to_string(), entry);
+		}
+		*history = json!({
+			"entries": entries,
+			"roots": (0..count).take(1).collect::<Vec<_>>(),
+			"current": current,
+			"next_id": count,
+		});
+	}
+	value["version"] = json!(4);
+	Ok(value)
+}
+
+/// Gives every line and image a new `id`, lines become `Stroke`s.
+///
+/// Lines and images in the history get ids independent of the ones on the pages.
+fn migrate_4_to_5(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	for_each_element(
+		&mut value,
+		&|line| *line = json!({ "id": new_element_id(), "points": line.take() }),
+		&|image| image["id"] = json!(new_element_id()),
+	);
+	value["version"] = json!(5);
+	Ok(value)
+}
+
+/// Adds the creation time, tool, style and label to every `Stroke`.
+///
+/// Existing strokes were drawn with round caps and joins, their creation time and tool are unknown.
+fn migrate_5_to_6(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	for_each_element(
+		&mut value,
+		&|line| {
+			line["created"] = json!(0);
+			line["tool"] = Value::Null;
+			line["style"] = json!({ "cap": "Round", "join": "Round", "dash": [] });
+			line["label"] = Value::Null;
+		},
+		&|_| {},
+	);
+	value["version"] = json!(6);
+	Ok(value)
+}
+
+/// Moves the lines and images of every page into a single visible layer.
+///
+/// Operations in the history that change lines or images refer to that layer.
+fn migrate_6_to_7(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	for_each_page_and_operation(
+		&mut value,
+		&|page| {
+			let layer = json!({
+				"id": new_element_id(),
+				"properties": { "name": "Layer 1", "visible": true, "locked": false, "opacity": 1.0 },
+				"lines": page.get_mut("lines").map(Value::take).unwrap_or_else(|| json!([])),
+				"images": page.get_mut("images").map(Value::take).unwrap_or_else(|| json!([])),
+			});
+			*page = json!({ "layers": [layer], "active_layer": 0 });
+		},
+		&|kind, operation| {
+			if let "AddStroke" | "ReplaceLines" | "MoveElements" | "AddImage" = kind {
+				operation["layer"] = json!(0);
+			}
+		},
+	);
+	value["version"] = json!(7);
+	Ok(value)
+}
+
+/// Replaces the indices of the lines and images in every `MoveElements` operation of the history with their ids.
+///
+/// The indices refer to the layer in the state after the operation, which is found by replaying the history from the stored pages.
+/// Translations do not change which elements a layer contains, so they are left out while replaying.
+fn migrate_7_to_8(
+	mut value: Value,
+	_image_data: &mut ImageData,
+	_warnings: &mut Vec<String>,
+) -> Result<Value> {
+	let mut history = value["history"].take();
+	if !history.is_null() {
+		let pages: Vec<Page> = serde_json::from_value(value["pages"].clone())?;
+		let mut replayed = history.clone();
+		for entry in object_values_mut(&mut replayed, "entries") {
+			for operation in leaf_operations(&mut entry["operation"]) {
+				if operation.get("MoveElements").is_some() {
+					*operation = json!({ "Group": [] });
+				}
+			}
+		}
+		let replayed: History = serde_json::from_value(replayed)?;
+		replayed.visit_states(&pages, |id, pages, _| {
+			let operation = &mut history["entries"][id.to_string()]["operation"];
+			for operation in leaf_operations(operation) {
+				if let Some(moved) = operation.get_mut("MoveElements") {
+					let layer = moved["page"]
+						.as_u64()
+						.zip(moved["layer"].as_u64())
+						.and_then(|(page, layer)| {
+							pages.get(page as usize)?.layers.get(layer as usize)
+						});
+					let line_ids: Vec<ElementId> = layer
+						.map(|layer| layer.lines().iter().map(|line| line.id).collect())
+						.unwrap_or_default();
+					let image_ids: Vec<ElementId> = layer
+						.map(|layer| layer.images.iter().map(|image| image.id).collect())
+						.unwrap_or_default();
+					for (key, ids) in [("lines", line_ids), ("images", image_ids)].iter() {
+						let resolved: Vec<ElementId> = array_mut(moved, key)
+							.filter_map(|index| ids.get(index.as_u64()? as usize).copied())
+							.collect();
+						moved[*key] = json!(resolved);
+					}
+				}
+			}
+		});
+	}
+	value["history"] = history;
+	value["version"] = json!(8);
+	Ok(value)
+}
+
+/// Calls `migrate_line` on every line and `migrate_image` on every image of the document `value`.
+///
+/// This includes the lines and images that are stored in the operations of the history.
+/// Only pages before version 7, which keep their lines and images without layers, are supported.
+fn for_each_element(
+	value: &mut Value,
+	migrate_line: &dyn Fn(&mut Value),
+	migrate_image: &dyn Fn(&mut Value),
+) {
+	for_each_page_and_operation(
+		value,
+		&|page| {
+			for line in array_mut(page, "lines") {
+				migrate_line(line);
+			}
+			for image in array_mut(page, "images") {
+				migrate_image(image);
+			}
+		},
+		&|kind, operation| match kind {
+			"AddStroke" => operation
+				.get_mut("stroke")
+				.into_iter()
+				.for_each(migrate_line),
+			"ReplaceLines" => {
+				for lines in ["removed", "inserted"].iter() {
+					array_mut(operation, lines).for_each(migrate_line);
+				}
+			}
+			"AddImage" => operation
+				.get_mut("image")
+				.into_iter()
+				.for_each(migrate_image),
+			_ => {}
+		},
+	);
+}
+
+/// Calls `migrate_page` on every page and `migrate_operation` with the kind of every operation of the document `value`.
+///
+/// This includes the pages that are stored in the operations of the history.
+/// Groups are not passed to `migrate_operation`, but the operations in them.
+fn for_each_page_and_operation(
+	value: &mut Value,
+	migrate_page: &dyn Fn(&mut Value),
+	migrate_operation: &dyn Fn(&str, &mut Value),
+) {
+	for page in array_mut(value, "pages") {
+		migrate_page(page);
+	}
+	let entries = value
+		.get_mut("history")
+		.and_then(|history| history.get_mut("entries"))
+		.and_then(Value::as_object_mut);
+	let mut operations: Vec<&mut Value> = entries
+		.into_iter()
+		.flat_map(|entries| entries.values_mut())
+		.filter_map(|entry| entry.get_mut("operation"))
+		.collect();
+	while let Some(operation) = operations.pop() {
+		let (kind, operation) = match operation
+			.as_object_mut()
+			.and_then(|object| object.iter_mut().next())
+		{
+			Some(variant) => variant,
+			None => continue,
+		};
+		match kind.as_str() {
+			"Group" => operations.extend(operation.as_array_mut().into_iter().flatten()),
+			"AddPage" | "RemovePage" => {
+				operation.get_mut("page").into_iter().for_each(migrate_page);
+				migrate_operation(kind, operation);
+			}
+			_ => migrate_operation(kind, operation),
+		}
+	}
+}
+
+/// Collects `operation`, or the operations in it if it is a group, including the ones in nested groups.
+fn leaf_operations(operation: &mut Value) -> Vec<&mut Value> {
+	let mut operations = vec![operation];
+	let mut leaves = Vec::new();
+	while let Some(operation) = operations.pop() {
+		if operation.get("Group").is_some() {
+			operations.extend(array_mut(operation, "Group"));
+		} else {
+			leaves.push(operation);
+		}
+	}
+	leaves
+}
+
+/// Iterates over the values of the object `key` in the object `value`, missing objects are empty.
+fn object_values_mut<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+	value
+		.get_mut(key)
+		.and_then(Value::as_object_mut)
+		.into_iter()
+		.flat_map(|object| object.values_mut())
+}
+
+/// Iterates over the elements of the array `key` in the object `value`, missing arrays are empty.
+fn array_mut<'a>(value: &'a mut Value, key: &str) -> impl Iterator<Item = &'a mut Value> {
+	value
+		.get_mut(key)
+		.and_then(Value::as_array_mut)
+		.into_iter()
+		.flatten()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::bundle::content_hash;
+	use crate::history::Operation;
+	use std::fs;
+
+	const POINT: &str =
+		r#"{ "position": [1.0, 2.0], "line_width": 3.0, "rgba": [0.0, 0.0, 0.0, 1.0] }"#;
+
+	#[test]
+	fn migrates_version_0_to_current() {
+		let image_path = std::env::temp_dir().join(format!("hnote-test-{}.png", new_element_id()));
+		fs::write(&image_path, b"image data").unwrap();
+		let missing_path = image_path.with_extension("missing.png");
+		let serialized = format!(
+			r#"[{{ "lines": [[{}]], "images": [{{ "path": {}, "position": [5.0, 6.0] }}, {{ "path": {}, "position": [7.0, 8.0] }}] }}]"#,
+			POINT,
+			serde_json::to_string(&image_path).unwrap(),
+			serde_json::to_string(&missing_path).unwrap()
+		);
+		let document = Document::from_json(&serialized, ImageData::new());
+		fs::remove_file(&image_path).unwrap();
+		let document = document.unwrap();
+
+		assert_eq!(document.version, CURRENT_VERSION);
+		assert!(document.history.is_none());
+		assert_eq!(document.pages.len(), 1);
+		let page = &document.pages[0];
+		assert_eq!(page.layers.len(), 1);
+		assert_eq!(page.active_layer, 0);
+		let layer = &page.layers[0];
+		assert_eq!(layer.properties.name, "Layer 1");
+		assert!(layer.properties.visible && !layer.properties.locked);
+		assert_eq!(layer.lines().len(), 1);
+		let line = &layer.lines()[0];
+		assert_eq!(
+			line.points,
+			vec![Drawpoint::new((1.0, 2.0), 3.0, [0.0, 0.0, 0.0, 1.0])]
+		);
+		assert_eq!(line.created, 0);
+		assert_eq!(line.tool, None);
+		assert_eq!(line.style, StrokeStyle::default());
+		assert_eq!(layer.images.len(), 1);
+		let image = &layer.images[0];
+		assert_eq!(image.position, (5.0, 6.0));
+		assert_eq!(image.hash, content_hash(b"image data"));
+		assert_eq!(*image.data, b"image data".to_vec());
+		assert_eq!(document.warnings.len(), 1);
+		assert!(document.warnings[0].contains(&*missing_path.to_string_lossy()));
+	}
+
+	#[test]
+	fn migrates_version_3_history_to_undo_tree() {
+		let serialized = format!(
+			r#"{{
+				"magic": "hnote",
+				"version": 3,
+				"pages": [{{ "lines": [[{point}]], "images": [] }}],
+				"history": {{
+					"done": [{{ "operation": {{ "AddStroke": {{ "page": 0, "index": 0, "stroke": [{point}] }} }}, "viewed_page": 0 }}],
+					"undone": [{{ "operation": {{ "AddStroke": {{ "page": 0, "index": 1, "stroke": [{point}] }} }}, "viewed_page": 0 }}]
+				}}
+			}}"#,
+			point = POINT
+		);
+		let document = Document::from_json(&serialized, ImageData::new()).unwrap();
+		let mut history = document.history.unwrap();
+		let mut pages = document.pages;
+
+		assert_eq!(history.tree(), vec![(0, 0), (1, 0)]);
+		assert_eq!(history.current(), Some(0));
+		assert_eq!(history.undo(&mut pages), Some(0));
+		assert!(pages[0].layers[0].lines().is_empty());
+		history.redo(&mut pages);
+		history.redo(&mut pages);
+		assert_eq!(pages[0].layers[0].lines().len(), 2);
+		assert_eq!(history.current(), Some(1));
+	}
+
+	#[test]
+	fn migrates_version_7_translations_to_ids() {
+		let point = || Drawpoint::new((1.0, 2.0), 3.0, [0.0, 0.0, 0.0, 1.0]);
+		let mut page = Page::new();
+		page.layers[0].push_line(Stroke::new(vec![point()]));
+		page.layers[0].push_line(Stroke::new(vec![point()]));
+		let added = Stroke::new(vec![point()]);
+		let moved = page.layers[0].lines()[0].id;
+		let mut pages = vec![page];
+		let mut history = History::new(10, usize::MAX);
+		for operation in [
+			Operation::AddStroke {
+				page: 0,
+				layer: 0,
+				index: 0,
+				stroke: added.clone(),
+			},
+			Operation::MoveElements {
+				page: 0,
+				layer: 0,
+				lines: vec![moved],
+				images: Vec::new(),
+				vector: (2.0, 3.0),
+			},
+		]
+		.iter()
+		{
+			operation.apply(&mut pages);
+			history.push(operation.clone(), 0);
+		}
+		let document = Document::new(pages).with_history(history);
+		// Before version 8 the moved lines were stored by their index after the translation.
+		let mut value: Value = serde_json::from_str(&document.to_json().unwrap()).unwrap();
+		value["version"] = json!(7);
+		value["history"]["entries"]["1"]["operation"]["MoveElements"]["lines"] = json!([1]);
+
+		let document = Document::from_json(&value.to_string(), ImageData::new()).unwrap();
+		let mut history = document.history.unwrap();
+		let mut pages = document.pages;
+		assert!(matches!(
+			history.entry(1).unwrap().operation,
+			Operation::MoveElements { ref lines, .. } if *lines == [moved]
+		));
+		history.undo(&mut pages);
+		let layer = &pages[0].layers[0];
+		assert_eq!(layer.lines()[0].id, added.id);
+		assert_eq!(layer.lines()[1].points[0].position, (1.0, 2.0));
+	}
+
+	#[test]
+	fn current_version_round_trips() {
+		let mut page = Page::new();
+		page.layers[0].push_line(Stroke::new(vec![Drawpoint::new(
+			(1.0, 2.0),
+			3.0,
+			[0.0, 0.0, 0.0, 1.0],
+		)]));
+		let document = Document::new(vec![page]);
+		let read = Document::from_json(&document.to_json().unwrap(), ImageData::new()).unwrap();
+		assert_eq!(
+			read.pages[0].layers[0].lines(),
+			document.pages[0].layers[0].lines()
+		);
+	}
+
+	#[test]
+	fn rejects_newer_and_foreign_documents() {
+		let newer = format!(
+			r#"{{ "magic": "hnote", "version": {}, "pages": [] }}"#,
+			CURRENT_VERSION + 1
+		);
+		assert!(matches!(
+			Document::from_json(&newer, ImageData::new()),
+			Err(Error::Format(_))
+		));
+		assert!(matches!(
+			Document::from_json(r#"{ "pages": [] }"#, ImageData::new()),
+			Err(Error::Format(_))
+		));
+	}
+}
@@ -0,0 +1,1993 @@
+use crate::editor::error::{show_error_dialog, Error, Result};
+use crate::editor::history_panel::HistoryPanel;
+use crate::editor::input::{InputAction, InputKind};
+use crate::editor::page_cache::{self, PageCache};
+use crate::editor::preferences::Preferences;
+use crate::editor::recovery::{Recovery, AUTOSAVE_INTERVAL};
+use crate::editor::render;
+use crate::editor::svg;
+use crate::editor::viewport::{Viewport, MAX_SCALE, MIN_SCALE, SCROLL_DISTANCE, ZOOM_STEP};
+use crate::editor::widgets::*;
+use cairo::Context;
+use gdk::*;
+use gdk_pixbuf::Pixbuf;
+use gio::ApplicationExt;
+use glib::*;
+use gtk::prelude::*;
+use gtk::*;
+use hnote::bundle;
+use hnote::document::Document;
+use hnote::history::{EntryId, History, Operation};
+use hnote::model::*;
+use hnote::pressure::PressureCurve;
+use hnote::tools::*;
+use serde_json::*;
+use std::boxed::Box as Heap;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+/// Width of the selection rectangle of `RectangleSelection` on the widget.
+const SELECTION_LINE_WIDTH: f64 = 5.0;
+
+/// Contains all top level groups of GTK widgets.
+#[derive(Clone, Debug)]
+struct ApplicationLayout {
+	pub vertical_pack_0: Box,
+	pub page_pack: Box,
+	pub layer_pack: Box,
+	pub tool_pack: Box,
+	pub horizontal_pack_1: Box,
+}
+
+impl ApplicationLayout {
+	pub fn new() -> Self {
+		Self {
+			vertical_pack_0: Box::new(Orientation::Vertical, 0),
+			page_pack: Box::new(Orientation::Vertical, 0),
+			layer_pack: Box::new(Orientation::Vertical, 6),
+			tool_pack: Box::new(Orientation::Horizontal, 0),
+			horizontal_pack_1: Box::new(Orientation::Horizontal, 0),
+		}
+	}
+}
+
+/// Contains information associated with direct drawing.
+#[derive(Clone, Debug)]
+struct DrawingInformation {
+	rgba: Rc<Mutex<[f64; 4]>>,
+	pen_is_active: Rc<Mutex<bool>>,
+	/// Whether a stylus is close to the screen, touches may be palms then.
+	pen_in_proximity: Rc<Mutex<bool>>,
+	/// Screen position of the last event of a drag that pans the canvas.
+	pan_position: Rc<Mutex<Option<(f64, f64)>>>,
+	pen_size: Rc<Mutex<f64>>,
+	cursor_position: Rc<Mutex<Option<(f64, f64)>>>,
+	current_draw_tool: Rc<Mutex<CurrentDrawTool>>,
+	/// Tool that was selected before the eraser end of a stylus was used, it is restored with the next other input.
+	replaced_draw_tool: Rc<Mutex<Option<CurrentDrawTool>>>,
+	line_pattern: Rc<Mutex<LinePattern>>,
+	tools: DrawTools,
+}
+
+impl DrawingInformation {
+	pub fn new() -> Self {
+		Self {
+			rgba: Rc::new(Mutex::new([0.0, 0.0, 0.0, 1.0])),
+			pen_is_active: Rc::new(Mutex::new(false)),
+			pen_in_proximity: Rc::new(Mutex::new(false)),
+			pan_position: Rc::new(Mutex::new(None)),
+			pen_size: Rc::new(Mutex::new(25.0)),
+			cursor_position: Rc::new(Mutex::new(Some((0.0, 0.0)))),
+			current_draw_tool: Rc::new(Mutex::new(CurrentDrawTool::Pencil)),
+			replaced_draw_tool: Rc::new(Mutex::new(None)),
+			line_pattern: Rc::new(Mutex::new(LinePattern::Solid)),
+			tools: DrawTools::default(),
+		}
+	}
+
+	/// Selects `eraser` while the input comes from the eraser end of a stylus and the previous tool for any other `source`.
+	///
+	/// The tool is never changed while the pen is active. Returns whether the tool changed.
+	pub fn follow_input_source(
+		&self,
+		source: Option<InputSource>,
+		eraser: CurrentDrawTool,
+	) -> bool {
+		if *self.pen_is_active.lock().unwrap() {
+			return false;
+		}
+		let mut current_draw_tool = self.current_draw_tool.lock().unwrap();
+		let mut replaced_draw_tool = self.replaced_draw_tool.lock().unwrap();
+		match (
+			source == Some(InputSource::Eraser),
+			replaced_draw_tool.take(),
+		) {
+			(true, None) => {
+				*replaced_draw_tool = Some(current_draw_tool.clone());
+				*current_draw_tool = eraser;
+				true
+			}
+			(true, Some(previous)) => {
+				*replaced_draw_tool = Some(previous);
+				false
+			}
+			(false, Some(previous)) => {
+				*current_draw_tool = previous;
+				true
+			}
+			(false, None) => false,
+		}
+	}
+
+	/// Creates the stroke that is drawn when the pen is pressed, with the current tool and line pattern.
+	pub fn new_stroke(&self) -> Stroke {
+		let tool = self.current_draw_tool.lock().unwrap().clone();
+		let pen_size = *self.pen_size.lock().unwrap();
+		Stroke::drawn_with(tool, self.line_pattern.lock().unwrap().style(pen_size))
+	}
+}
+
+/// The `DrawTool`s of the editor, they keep their state between events.
+#[derive(Clone, Debug, Default)]
+struct DrawTools {
+	pencil: Rc<Mutex<Pencil>>,
+	eraser: Rc<Mutex<Eraser>>,
+	line_eraser: Rc<Mutex<LineEraser>>,
+	line_tool: Rc<Mutex<LineTool>>,
+	drag: Rc<Mutex<Drag>>,
+	rectangle_selection: Rc<Mutex<RectangleSelection>>,
+	clear: Rc<Mutex<Clear>>,
+}
+
+impl DrawTools {
+	/// The tool that `draw_tool` stands for.
+	pub fn get(&self, draw_tool: &CurrentDrawTool) -> Rc<Mutex<dyn DrawTool>> {
+		match draw_tool {
+			CurrentDrawTool::Pencil => Rc::clone(&self.pencil) as _,
+			CurrentDrawTool::Eraser => Rc::clone(&self.eraser) as _,
+			CurrentDrawTool::LineEraser => Rc::clone(&self.line_eraser) as _,
+			CurrentDrawTool::LineTool => Rc::clone(&self.line_tool) as _,
+			CurrentDrawTool::Drag => Rc::clone(&self.drag) as _,
+			CurrentDrawTool::RectangleSelection => Rc::clone(&self.rectangle_selection) as _,
+			CurrentDrawTool::Clear => Rc::clone(&self.clear) as _,
+		}
+	}
+}
+
+/// Enum representation of the line patterns that new strokes can be drawn with.
+#[derive(Clone, Copy, Debug)]
+enum LinePattern {
+	Solid,
+	Dashed,
+	Dotted,
+}
+
+impl LinePattern {
+	const ALL: [(LinePattern, &'static str); 3] = [
+		(LinePattern::Solid, "Solid"),
+		(LinePattern::Dashed, "Dashed"),
+		(LinePattern::Dotted, "Dotted"),
+	];
+
+	/// Style of a stroke with the pattern of `self`, the dashes are scaled to `pen_size`.
+	pub fn style(self, pen_size: f64) -> StrokeStyle {
+		let dash = match self {
+			LinePattern::Solid => Vec::new(),
+			LinePattern::Dashed => vec![pen_size * 3.0, pen_size * 2.0],
+			LinePattern::Dotted => vec![0.0, pen_size * 2.0],
+		};
+		StrokeStyle {
+			dash,
+			..StrokeStyle::default()
+		}
+	}
+}
+
+/// Implements Handwritten Notetaking Editor's setup, data and logic.
+#[derive(Clone, Debug)]
+pub struct Application {
+	current_page: Rc<Mutex<usize>>,
+	pages: Rc<Mutex<Vec<Page>>>,
+	history: Rc<Mutex<History>>,
+	history_panel: HistoryPanel,
+	/// Page, layer and tool that the pen was pressed with, the tool records its change of the layer when the pen is released.
+	stroke_start: Rc<Mutex<Option<(usize, usize, CurrentDrawTool)>>>,
+	application_layout: ApplicationLayout,
+	area: DrawingArea,
+	/// Scale and translation of the page on `self.area`.
+	viewport: Rc<Mutex<Viewport>>,
+	/// Size of the pages in page coordinates.
+	canvas_size: Rc<Mutex<(f64, f64)>>,
+	/// Shows and sets the scale of `self.viewport` in percent.
+	zoom_button: SpinButton,
+	drawing_information: DrawingInformation,
+	window: ApplicationWindow,
+	current_path: Rc<Mutex<Option<PathBuf>>>,
+	image_buffer: Rc<Mutex<ImageBuffer>>,
+	page_cache: Rc<Mutex<PageCache>>,
+	recovery: Recovery,
+	autosave_pending: Rc<Mutex<bool>>,
+	preferences: Rc<Mutex<Preferences>>,
+}
+
+impl Application {
+	/// Initializes all necessary values and instantiates the `gtk::Application`.
+	pub fn new(gtk_application: &gtk::Application) -> Self {
+		let window = gtk::ApplicationWindow::new(gtk_application);
+		let current_page = Rc::new(Mutex::new(0));
+		let application_layout = ApplicationLayout::new();
+		let area = DrawingArea::new();
+		// Without touch events GTK emulates pointer events for touches, so all devices share the pointer handlers.
+		area.add_events(EventMask::ALL_EVENTS_MASK & !EventMask::TOUCH_MASK);
+		let drawing_information = DrawingInformation::new();
+		let image_buffer = Rc::new(Mutex::new(ImageBuffer::new()));
+		let pages = Rc::new(Mutex::new(vec![Page::new()]));
+		let preferences = Preferences::load();
+		let history = Rc::new(Mutex::new(preferences.history()));
+		let current_path = Rc::new(Mutex::new(None));
+		let application = Self {
+			current_page,
+			pages,
+			history,
+			history_panel: HistoryPanel::new(&window),
+			stroke_start: Rc::new(Mutex::new(None)),
+			application_layout,
+			area,
+			viewport: Rc::new(Mutex::new(Viewport::new())),
+			canvas_size: Rc::new(Mutex::new((1920.0, 1080.0))),
+			zoom_button: SpinButton::with_range(MIN_SCALE * 100.0, MAX_SCALE * 100.0, 10.0),
+			drawing_information,
+			window: window.clone(),
+			current_path,
+			image_buffer,
+			page_cache: Rc::new(Mutex::new(PageCache::new())),
+			recovery: Recovery::new(),
+			autosave_pending: Rc::new(Mutex::new(false)),
+			preferences: Rc::new(Mutex::new(preferences)),
+		};
+		application.build_ui();
+		application.recover(gtk_application);
+		application
+	}
+
+	/// Sets basic `self.window` variables and shows the `gtk::ApplicationWindow`.
+	///
+	/// Invokes `self.application_layout()`.
+	pub fn build_ui(&self) {
+		self.window.set_title("Handwritten notetaking editor");
+		self.window.set_border_width(10);
+		self.window.set_position(WindowPosition::Center);
+		self.window.set_default_size(800, 600);
+
+		self.application_layout(&self.window);
+
+		self.window.show_all();
+	}
+
+	/// Composes all top level groups to `self.window`.
+	///
+	/// Invokes `self.drawing_mechanics()`.
+	fn application_layout(&self, window: &ApplicationWindow) {
+		let menu_bar = self.menu_bar();
+
+		self.application_layout
+			.vertical_pack_0
+			.pack_start(&menu_bar, false, false, 0);
+		self.application_layout.vertical_pack_0.pack_start(
+			&self.application_layout.tool_pack,
+			false,
+			false,
+			0,
+		);
+		self.application_layout.vertical_pack_0.pack_start(
+			&self.application_layout.horizontal_pack_1,
+			true,
+			true,
+			0,
+		);
+		self.application_layout.horizontal_pack_1.pack_start(
+			&self.application_layout.page_pack,
+			false,
+			false,
+			0,
+		);
+		self.application_layout
+			.horizontal_pack_1
+			.pack_start(&self.area, true, true, 0);
+		self.application_layout.horizontal_pack_1.pack_start(
+			&self.application_layout.layer_pack,
+			false,
+			false,
+			0,
+		);
+
+		self.drawing_mechanics();
+
+		window.add(&self.application_layout.vertical_pack_0);
+	}
+
+	/// Composes a `gtk::MenuBar` with basic functionality.
+	fn menu_bar(&self) -> MenuBar {
+		let menu_bar = MenuBar::new();
+		self.file_menu(&menu_bar);
+		self.page_menu(&menu_bar);
+		self.view_menu(&menu_bar);
+		menu_bar
+	}
+
+	/// Composes a file menu with basic export and import functionality.
+	fn file_menu(&self, menu_bar: &MenuBar) {
+		let file_menu = Menu::new();
+		let file = MenuItem::with_label("File");
+		let open_file = MenuItem::new();
+		open_file.add(&Label::new(Some("Open...")));
+
+		open_file.connect_activate(clone!(@strong self as this => move |_| {
+			this.connect_file_dialog(FileChooserAction::Open, Heap::new(clone!(@strong this => move |current_path| {
+				match this.load_file(&current_path) {
+					Ok(()) => *this.current_path.lock().unwrap() = Some(current_path.clone()),
+					Err(error) => this.show_error(&format!("Could not open {}.", current_path.display()), &error),
+				}
+			})));
+		}));
+
+		let save_file = MenuItem::new();
+		save_file.add(&Label::new(Some("Save...")));
+		save_file.connect_activate(clone!(@strong self as this => move |_| {
+			this.connect_path_or_file_dialog(FileChooserAction::Save, Heap::new(clone!(@strong this => move |current_path| {
+				match this.save_file(&current_path) {
+					Ok(()) => *this.current_path.lock().unwrap() = Some(current_path.clone()),
+					Err(error) => this.show_error(&format!("Could not save {}.", current_path.display()), &error),
+				}
+			})));
+		}));
+
+		let save_as_file = MenuItem::new();
+		save_as_file.add(&Label::new(Some("Save as...")));
+		save_as_file.connect_activate(clone!(@strong self as this => move |_| {
+			this.connect_file_dialog(FileChooserAction::Save, Heap::new(clone!(@strong this => move |current_path| {
+				match this.save_file(&current_path) {
+					Ok(()) => *this.current_path.lock().unwrap() = Some(current_path.clone()),
+					Err(error) => this.show_error(&format!("Could not save {}.", current_path.display()), &error),
+				}
+			})));
+		}));
+
+		let import_image = MenuItem::new();
+		import_image.add(&Label::new(Some("Import image...")));
+		import_image.connect_activate(clone!(@strong self as this => move |_| {
+			this.connect_filtered_file_dialog(FileChooserAction::Open, &Self::image_filters(), Heap::new(clone!(@strong this => move |current_path| {
+				if let Err(error) = this.import_image(&current_path) {
+					this.show_error(&format!("Could not import {}.", current_path.display()), &error);
+				}
+			})));
+		}));
+
+		let export_png = MenuItem::new();
+		export_png.add(&Label::new(Some("Export as PNG...")));
+		export_png.connect_activate(clone!(@strong self as this => move |_| {
+			this.export_png();
+		}));
+
+		let export_pdf = MenuItem::new();
+		export_pdf.add(&Label::new(Some("Export as PDF...")));
+		export_pdf.connect_activate(clone!(@strong self as this => move |_| {
+			this.export_pdf();
+		}));
+
+		let export_svg = MenuItem::new();
+		export_svg.add(&Label::new(Some("Export page as SVG...")));
+		export_svg.connect_activate(clone!(@strong self as this => move |_| {
+			this.export_svg(false);
+		}));
+
+		let export_all_svg = MenuItem::new();
+		export_all_svg.add(&Label::new(Some("Export all pages as SVG...")));
+		export_all_svg.connect_activate(clone!(@strong self as this => move |_| {
+			this.export_svg(true);
+		}));
+
+		let preferences = MenuItem::new();
+		preferences.add(&Label::new(Some("Preferences...")));
+		preferences.connect_activate(clone!(@strong self as this => move |_| {
+			this.preferences_dialog();
+		}));
+
+		file_menu.append(&open_file);
+		file_menu.append(&save_file);
+		file_menu.append(&save_as_file);
+		file_menu.append(&import_image);
+		file_menu.append(&export_png);
+		file_menu.append(&export_pdf);
+		file_menu.append(&export_svg);
+		file_menu.append(&export_all_svg);
+		file_menu.append(&preferences);
+		file.set_submenu(Some(&file_menu));
+		menu_bar.append(&file);
+	}
+
+	/// Opens a dialog to edit `self.preferences`.
+	///
+	/// The preferences are stored when the dialog is closed.
+	fn preferences_dialog(&self) {
+		let dialog = Dialog::with_buttons(
+			Some("Preferences"),
+			Some(&self.window),
+			DialogFlags::DESTROY_WITH_PARENT,
+			&[("Close", ResponseType::Close)],
+		);
+		dialog.set_default_response(ResponseType::Close);
+		let content_area = dialog.get_content_area();
+		let preferences = self.preferences.lock().unwrap().clone();
+
+		let backup_count_label = Label::new(Some("Backups per notebook"));
+		let backup_count = SpinButton::with_range(0.0, 20.0, 1.0);
+		backup_count.set_value(preferences.backup_count as f64);
+		content_area.pack_start(&backup_count_label, false, false, 0);
+		content_area.pack_start(&backup_count, false, false, 0);
+
+		let history_depth_label = Label::new(Some("Undo steps"));
+		let history_depth = SpinButton::with_range(1.0, 100000.0, 10.0);
+		history_depth.set_value(preferences.history_depth as f64);
+		content_area.pack_start(&history_depth_label, false, false, 0);
+		content_area.pack_start(&history_depth, false, false, 0);
+
+		let history_memory_label = Label::new(Some("Undo memory (MiB)"));
+		let history_memory = SpinButton::with_range(1.0, 4096.0, 1.0);
+		history_memory.set_value(preferences.history_memory as f64);
+		content_area.pack_start(&history_memory_label, false, false, 0);
+		content_area.pack_start(&history_memory, false, false, 0);
+
+		let stored_history_depth_label = Label::new(Some("Undo steps stored in notebooks"));
+		let stored_history_depth = SpinButton::with_range(0.0, 100000.0, 10.0);
+		stored_history_depth.set_value(preferences.stored_history_depth as f64);
+		content_area.pack_start(&stored_history_depth_label, false, false, 0);
+		content_area.pack_start(&stored_history_depth, false, false, 0);
+
+		let pressure_exponent_label = Label::new(Some("Pressure curve exponent"));
+		let pressure_exponent = SpinButton::with_range(0.1, 5.0, 0.1);
+		pressure_exponent.set_value(preferences.pressure_curve.exponent);
+		content_area.pack_start(&pressure_exponent_label, false, false, 0);
+		content_area.pack_start(&pressure_exponent, false, false, 0);
+
+		let minimum_width_label = Label::new(Some("Width at the lightest pressure"));
+		let minimum_width = SpinButton::with_range(0.0, 1.0, 0.05);
+		minimum_width.set_value(preferences.pressure_curve.minimum_width);
+		content_area.pack_start(&minimum_width_label, false, false, 0);
+		content_area.pack_start(&minimum_width, false, false, 0);
+
+		let maps_opacity = CheckButton::with_label("Pressure changes the opacity");
+		maps_opacity.set_active(preferences.pressure_curve.maps_opacity);
+		content_area.pack_start(&maps_opacity, false, false, 0);
+
+		let minimum_opacity_label = Label::new(Some("Opacity at the lightest pressure"));
+		let minimum_opacity = SpinButton::with_range(0.0, 1.0, 0.05);
+		minimum_opacity.set_value(preferences.pressure_curve.minimum_opacity);
+		content_area.pack_start(&minimum_opacity_label, false, false, 0);
+		content_area.pack_start(&minimum_opacity, false, false, 0);
+
+		let stylus_eraser_label = Label::new(Some("Eraser end of a stylus"));
+		let stylus_eraser = ComboBoxText::new();
+		stylus_eraser.append_text("Eraser");
+		stylus_eraser.append_text("Line Eraser");
+		stylus_eraser.set_active(Some(match preferences.stylus_eraser {
+			CurrentDrawTool::LineEraser => 1,
+			_ => 0,
+		}));
+		content_area.pack_start(&stylus_eraser_label, false, false, 0);
+		content_area.pack_start(&stylus_eraser, false, false, 0);
+
+		let input_kinds = [
+			(InputKind::Pen, "Pen"),
+			(InputKind::Touch, "Touch"),
+			(InputKind::Mouse, "Mouse"),
+			(InputKind::Touchpad, "Touchpad"),
+		];
+		let mut input_policy = preferences.input_policy;
+		let input_actions: Vec<(InputKind, ComboBoxText)> = input_kinds
+			.iter()
+			.map(|(kind, label)| {
+				let input_action = ComboBoxText::new();
+				for (_, action_label) in InputAction::ALL.iter() {
+					input_action.append_text(action_label);
+				}
+				let current_action = *input_policy.action_mut(*kind);
+				input_action.set_active(
+					InputAction::ALL
+						.iter()
+						.position(|(action, _)| *action == current_action)
+						.map(|index| index as u32),
+				);
+				content_area.pack_start(
+					&Label::new(Some(&format!("{} input", label))),
+					false,
+					false,
+					0,
+				);
+				content_area.pack_start(&input_action, false, false, 0);
+				(*kind, input_action)
+			})
+			.collect();
+		let ignores_touch_near_pen =
+			CheckButton::with_label("Ignore touches while a pen is near the screen");
+		ignores_touch_near_pen.set_active(input_policy.ignores_touch_near_pen);
+		content_area.pack_start(&ignores_touch_near_pen, false, false, 0);
+		dialog.show_all();
+
+		dialog.connect_response(clone!(@strong self as this, @strong backup_count, @strong history_depth, @strong history_memory, @strong stored_history_depth, @strong pressure_exponent, @strong minimum_width, @strong maps_opacity, @strong minimum_opacity, @strong stylus_eraser, @strong ignores_touch_near_pen => move |dialog, _| {
+			let preferences = {
+				let mut preferences = this.preferences.lock().unwrap();
+				preferences.backup_count = backup_count.get_value_as_int() as usize;
+				preferences.history_depth = history_depth.get_value_as_int() as usize;
+				preferences.history_memory = history_memory.get_value_as_int() as usize;
+				preferences.stored_history_depth = stored_history_depth.get_value_as_int() as usize;
+				preferences.pressure_curve = PressureCurve {
+					exponent: pressure_exponent.get_value(),
+					minimum_width: minimum_width.get_value(),
+					maps_opacity: maps_opacity.get_active(),
+					minimum_opacity: minimum_opacity.get_value(),
+				};
+				preferences.stylus_eraser = match stylus_eraser.get_active() {
+					Some(1) => CurrentDrawTool::LineEraser,
+					_ => CurrentDrawTool::Eraser,
+				};
+				let mut input_policy = input_policy;
+				for (kind, input_action) in input_actions.iter() {
+					if let Some(index) = input_action.get_active() {
+						*input_policy.action_mut(*kind) = InputAction::ALL[index as usize].0;
+					}
+				}
+				input_policy.ignores_touch_near_pen = ignores_touch_near_pen.get_active();
+				preferences.input_policy = input_policy;
+				preferences.clone()
+			};
+			this.history
+				.lock()
+				.unwrap()
+				.set_limits(preferences.history_depth, preferences.history_memory_bytes());
+			if let Err(error) = preferences.save() {
+				this.show_error("Could not store the preferences.", &error);
+			}
+			dialog.close();
+		}));
+	}
+
+	/// Composes a page menu with page layouting settings.
+	fn page_menu(&self, menu_bar: &MenuBar) {
+		let page_menu = Menu::new();
+		let page = MenuItem::with_label("Page");
+		let change_canvas_size = MenuItem::new();
+		change_canvas_size.add(&Label::new(Some("Change canvas size")));
+
+		change_canvas_size.connect_activate(clone!(@strong self as this => move |_| {
+			let dialog = Dialog::with_buttons(
+				Some("Colors"),
+				Some(&this.window.clone()),
+				DialogFlags::DESTROY_WITH_PARENT,
+				&[("Close", ResponseType::Close)],
+			);
+			dialog.set_default_response(ResponseType::Close);
+			let content_area = dialog.get_content_area();
+
+			let width_label = Label::new(Some("Width"));
+			let height_label = Label::new(Some("Height"));
+			let width_entry = Entry::new();
+			let height_entry = Entry::new();
+			content_area.pack_start(&width_label, false, false, 0);
+			content_area.pack_start(&width_entry, false, false, 0);
+			content_area.pack_start(&height_label, false, false, 0);
+			content_area.pack_start(&height_entry, false, false, 0);
+			dialog.show_all();
+
+			dialog.connect_response(clone!(@strong this, @strong width_entry, @strong height_entry => move |dialog, _| {
+				match width_entry.get_text().as_str().parse(){
+					Ok(width) => {
+						match height_entry.get_text().as_str().parse() {
+							Ok(height) => {
+								*this.canvas_size.lock().unwrap() = (width, height);
+								this.area.queue_draw();
+							},
+							Err(_) => {
+								let dialog = MessageDialog::new::<ApplicationWindow>(
+									None,
+									DialogFlags::DESTROY_WITH_PARENT,
+									MessageType::Info,
+									ButtonsType::None,
+									"Height could not be parsed."
+								);
+								dialog.show_all();
+							},
+						}
+					},
+					Err(_) => {
+						let dialog = MessageDialog::new::<ApplicationWindow>(
+							None,
+							DialogFlags::DESTROY_WITH_PARENT,
+							MessageType::Info,
+							ButtonsType::None,
+							"Width could not be parsed."
+						);
+						dialog.show_all();
+					},
+				}
+				dialog.close()
+			}));
+		}));
+
+		page_menu.append(&change_canvas_size);
+		page.set_submenu(Some(&page_menu));
+		menu_bar.append(&page);
+	}
+
+	/// Composes a view menu to zoom the canvas, with keyboard shortcuts.
+	fn view_menu(&self, menu_bar: &MenuBar) {
+		let view_menu = Menu::new();
+		let view = MenuItem::with_label("View");
+		let accel_group = AccelGroup::new();
+		self.window.add_accel_group(&accel_group);
+
+		let zoom_in = MenuItem::with_label("Zoom in");
+		zoom_in.add_accelerator(
+			"activate",
+			&accel_group,
+			*keys::constants::plus,
+			ModifierType::CONTROL_MASK,
+			AccelFlags::VISIBLE,
+		);
+		// Ctrl+= is the same key as Ctrl++ on many layouts, but without Shift.
+		zoom_in.add_accelerator(
+			"activate",
+			&accel_group,
+			*keys::constants::equal,
+			ModifierType::CONTROL_MASK,
+			AccelFlags::empty(),
+		);
+		zoom_in.connect_activate(clone!(@strong self as this => move |_| {
+			let viewport = *this.viewport.lock().unwrap();
+			this.zoom_to(viewport.scale * ZOOM_STEP, viewport.center());
+		}));
+
+		let zoom_out = MenuItem::with_label("Zoom out");
+		zoom_out.add_accelerator(
+			"activate",
+			&accel_group,
+			*keys::constants::minus,
+			ModifierType::CONTROL_MASK,
+			AccelFlags::VISIBLE,
+		);
+		zoom_out.connect_activate(clone!(@strong self as this => move |_| {
+			let viewport = *this.viewport.lock().unwrap();
+			this.zoom_to(viewport.scale / ZOOM_STEP, viewport.center());
+		}));
+
+		let actual_size = MenuItem::with_label("Actual size");
+		actual_size.add_accelerator(
+			"activate",
+			&accel_group,
+			*keys::constants::_0,
+			ModifierType::CONTROL_MASK,
+			AccelFlags::VISIBLE,
+		);
+		actual_size.connect_activate(clone!(@strong self as this => move |_| {
+			let viewport = *this.viewport.lock().unwrap();
+			this.zoom_to(1.0, viewport.center());
+		}));
+
+		view_menu.append(&zoom_in);
+		view_menu.append(&zoom_out);
+		view_menu.append(&actual_size);
+		view.set_submenu(Some(&view_menu));
+		menu_bar.append(&view);
+	}
+
+	/// Withholds all drawing specific methods and variables.
+	///
+	/// Connects basic canvas input and drawing.
+	///
+	/// Invokes `self.reload_page_pack()`, `self.reload_layer_pack()`, `self.undo_redo()`, `self.manage_drawing_modes()`, `self.position_pointer()`,
+	/// `self.zoom_mechanics()`.
+	fn drawing_mechanics(&self) {
+		self.reload_page_pack();
+		self.reload_layer_pack();
+
+		self.undo_redo();
+
+		let pen_size = Scale::with_range(Orientation::Horizontal, 0.5, 50.0, 0.5);
+		pen_size.set_value(*self.drawing_information.pen_size.lock().unwrap());
+		pen_size.connect_change_value(
+			clone!(@strong self.drawing_information.pen_size as pen_size => move |_, _, v| {
+				*pen_size.lock().unwrap() = v;
+				Inhibit(false)
+			}),
+		);
+
+		self.application_layout
+			.tool_pack
+			.pack_start(&pen_size, true, true, 0);
+
+		let line_pattern = ComboBoxText::new();
+		for (_, label) in LinePattern::ALL.iter() {
+			line_pattern.append_text(label);
+		}
+		line_pattern.set_active(Some(0));
+		line_pattern.connect_changed(
+			clone!(@strong self.drawing_information.line_pattern as pattern => move |line_pattern| {
+				if let Some(active) = line_pattern.get_active() {
+					*pattern.lock().unwrap() = LinePattern::ALL[active as usize].0;
+				}
+			}),
+		);
+		self.application_layout
+			.tool_pack
+			.pack_start(&line_pattern, false, false, 0);
+
+		self.area
+			.connect_button_press_event(clone!(@strong self as this => move |_, e| {
+				if e.get_event_type() != EventType::ButtonPress {
+					return Inhibit(false);
+				}
+				match this.input_action(e.get_source_device()) {
+					InputAction::Draw => {}
+					InputAction::Pan => {
+						*this.drawing_information.pan_position.lock().unwrap() = Some(e.get_root());
+						return Inhibit(false);
+					}
+					InputAction::Ignore => return Inhibit(false),
+				}
+				this.follow_input_source(e.get_source_device());
+				let stroke = this.drawing_information.new_stroke();
+				let mut pages = this.pages.lock().unwrap();
+				let current_page = this.current_page.lock().unwrap();
+				let mut pen_is_active = this.drawing_information.pen_is_active.lock().unwrap();
+				*pen_is_active = true;
+				let active_layer = pages[*current_page].active_layer_index();
+				if let Some(layer) = pages[*current_page].editable_layer_mut() {
+					let current_draw_tool = this.drawing_information.current_draw_tool.lock().unwrap();
+					*this.stroke_start.lock().unwrap() = Some((*current_page, active_layer, current_draw_tool.clone()));
+					this.drawing_information.tools.get(&current_draw_tool).lock().unwrap().start(layer, stroke);
+					if matches!(*current_draw_tool, CurrentDrawTool::Pencil | CurrentDrawTool::LineTool) {
+						this.page_cache.lock().unwrap().start_stroke(*current_page, active_layer, layer.lines().len() - 1);
+					}
+				}
+				Inhibit(false)
+			}));
+
+		self.area
+			.connect_proximity_in_event(clone!(@strong self as this => move |_, e| {
+				let source = e.get_source_device().map(|device| device.get_source());
+				if InputKind::from_source(source) == InputKind::Pen {
+					*this.drawing_information.pen_in_proximity.lock().unwrap() = true;
+				}
+				if this.input_action(e.get_source_device()) == InputAction::Draw {
+					this.follow_input_source(e.get_source_device());
+				}
+				Inhibit(false)
+			}));
+
+		self.area.connect_proximity_out_event(
+			clone!(@strong self.drawing_information.pen_in_proximity as pen_in_proximity => move |_, _| {
+				*pen_in_proximity.lock().unwrap() = false;
+				Inhibit(false)
+			}),
+		);
+
+		self.area
+			.connect_button_release_event(clone!(@strong self as this => move |_, e| {
+				match this.input_action(e.get_source_device()) {
+					InputAction::Draw => *this.drawing_information.pen_is_active.lock().unwrap() = false,
+					InputAction::Pan => *this.drawing_information.pan_position.lock().unwrap() = None,
+					InputAction::Ignore => {}
+				}
+				Inhibit(false)
+			}));
+
+		self.area
+			.connect_draw(clone!(@strong self as this => move |_, cr| {
+				this.context_drawing_mechanics(cr);
+				Inhibit(false)
+			}));
+
+		self.manage_drawing_modes();
+
+		self.position_pointer();
+
+		self.zoom_mechanics();
+	}
+
+	/// Connects zooming with Ctrl+scroll and `self.zoom_button`, scrolling without Ctrl pans the canvas.
+	///
+	/// Adds `self.zoom_button` to `self.application_layout.tool_pack`.
+	fn zoom_mechanics(&self) {
+		self.area
+			.connect_scroll_event(clone!(@strong self as this => move |_, e| {
+				let (mut dx, mut dy) = match e.get_direction() {
+					ScrollDirection::Up => (0.0, -1.0),
+					ScrollDirection::Down => (0.0, 1.0),
+					ScrollDirection::Left => (-1.0, 0.0),
+					ScrollDirection::Right => (1.0, 0.0),
+					_ => e.get_delta(),
+				};
+				let state = e.get_state();
+				if state.contains(ModifierType::CONTROL_MASK) {
+					let scale = this.viewport.lock().unwrap().scale;
+					this.zoom_to(scale * ZOOM_STEP.powf(-dy), e.get_position());
+				} else {
+					if state.contains(ModifierType::SHIFT_MASK) {
+						std::mem::swap(&mut dx, &mut dy);
+					}
+					this.pan_viewport((-dx * SCROLL_DISTANCE, -dy * SCROLL_DISTANCE));
+				}
+				Inhibit(true)
+			}));
+
+		self.zoom_button.set_digits(0);
+		self.zoom_button.set_value(100.0);
+		self.zoom_button.set_tooltip_text(Some("Zoom in percent"));
+		self.zoom_button
+			.connect_value_changed(clone!(@strong self as this => move |zoom_button| {
+				let center = this.viewport.lock().unwrap().center();
+				this.zoom_to(zoom_button.get_value() / 100.0, center);
+			}));
+		self.application_layout
+			.tool_pack
+			.pack_start(&self.zoom_button, false, false, 0);
+	}
+
+	/// Scales the canvas to `scale`, the page position at the widget position `center` stays in place.
+	fn zoom_to(&self, scale: f64, center: (f64, f64)) {
+		let scale = {
+			let mut viewport = self.viewport.lock().unwrap();
+			if viewport.scale == scale {
+				return;
+			}
+			viewport.zoom_to(scale, center);
+			viewport.scale
+		};
+		self.zoom_button.set_value(scale * 100.0);
+		self.area.queue_draw();
+	}
+
+	/// Moves the page on the canvas by `distance` pixels.
+	fn pan_viewport(&self, distance: (f64, f64)) {
+		self.viewport.lock().unwrap().pan(distance);
+		self.area.queue_draw();
+	}
+
+	/// Action of the input policy in `self.preferences` for the events of `device`.
+	fn input_action(&self, device: Option<gdk::Device>) -> InputAction {
+		let kind = InputKind::from_source(device.map(|device| device.get_source()));
+		let pen_in_proximity = *self.drawing_information.pen_in_proximity.lock().unwrap();
+		self.preferences
+			.lock()
+			.unwrap()
+			.input_policy
+			.action(kind, pen_in_proximity)
+	}
+
+	/// Moves the page along with a panning drag that moved to the screen position `root_position`.
+	///
+	/// Nothing happens if no panning drag was started by a button press.
+	fn pan_canvas(&self, root_position: (f64, f64)) {
+		let previous_position = self
+			.drawing_information
+			.pan_position
+			.lock()
+			.unwrap()
+			.replace(root_position);
+		if let Some(previous_position) = previous_position {
+			self.pan_viewport((
+				root_position.0 - previous_position.0,
+				root_position.1 - previous_position.1,
+			));
+		}
+	}
+
+	/// Switches to the eraser tool of `self.preferences` while the events of the canvas come from the eraser end of a stylus.
+	///
+	/// The previous tool is restored as soon as the pen tip or another `device` is used.
+	fn follow_input_source(&self, device: Option<gdk::Device>) {
+		let source = device.map(|device| device.get_source());
+		let eraser = self.preferences.lock().unwrap().stylus_eraser.clone();
+		if self.drawing_information.follow_input_source(source, eraser) {
+			self.area.queue_draw();
+		}
+	}
+
+	/// Specifies how a context is composed out of `self`.
+	///
+	/// Draws the `self.current_page` with the images in `self.image_buffer`, every stroke in its own `StrokeStyle`.
+	/// Finished content is painted from `self.page_cache`, only the stroke that is being drawn is drawn again.
+	/// The page is shown through `self.viewport`, which follows the size of `self.area`.
+	fn context_drawing_mechanics(&self, cr: &Context) {
+		let pages = self.pages.lock().unwrap();
+		let current_page = self.current_page.lock().unwrap();
+		let image_buffer = self.image_buffer.lock().unwrap();
+		let viewport = {
+			let mut viewport = self.viewport.lock().unwrap();
+			viewport.size = (
+				self.area.get_allocated_width(),
+				self.area.get_allocated_height(),
+			);
+			*viewport
+		};
+		self.page_cache.lock().unwrap().draw(
+			cr,
+			*current_page,
+			&pages[*current_page],
+			&image_buffer,
+			self.canvas_size(),
+			viewport,
+		);
+	}
+
+	/// Saves `self` in a bundle file that embeds all images.
+	///
+	/// The pages are wrapped in a `Document` of the newest version.
+	/// The file is replaced atomically, previous versions are kept as rotating backups.
+	/// The file path is declared by `path_puf`.
+	fn save_file(&self, path_puf: &PathBuf) -> Result<()> {
+		let backup_count = self.preferences.lock().unwrap().backup_count;
+		Ok(bundle::save_bundle(
+			path_puf,
+			&self.document(),
+			backup_count,
+		)?)
+	}
+
+	/// Wraps a copy of `self.pages` in a `Document`.
+	///
+	/// The newest operations of `self.history` are stored as well, as configured by `stored_history_depth`.
+	/// Operations that can be redone are not stored.
+	fn document(&self) -> Document {
+		let document = Document::new(self.pages.lock().unwrap().clone());
+		let stored_history_depth = self.preferences.lock().unwrap().stored_history_depth;
+		if stored_history_depth == 0 {
+			return document;
+		}
+		document.with_history(self.history.lock().unwrap().compacted(stored_history_depth))
+	}
+
+	/// Loads `self` from a bundle file.
+	///
+	/// Documents of older versions are migrated to the newest version.
+	/// Images are restored from the embedded data, the original image files are not accessed.
+	/// The file path is declared by `path_puf`.
+	fn load_file(&self, path_puf: &PathBuf) -> Result<()> {
+		let serialized = std::fs::read(path_puf)?;
+		self.load_document(bundle::read_bundle(&serialized)?)
+	}
+
+	/// Replaces the pages of `self` with the pages of `document`.
+	///
+	/// The history stored in `document` is restored with the limits of `self.preferences`, so its operations can be undone.
+	/// `self` is only changed if the images of the first page could be decoded.
+	/// Content that had to be left out while reading `document` is listed in a warning dialog.
+	fn load_document(&self, document: Document) -> Result<()> {
+		let image_buffer = page_image_buffer(&document.pages[0])?;
+		let warnings = document.warnings.join("\n");
+		{
+			let mut pages = self.pages.lock().unwrap();
+			let mut current_page = self.current_page.lock().unwrap();
+			let preferences = self.preferences.lock().unwrap();
+			*pages = document.pages;
+			*current_page = 0;
+			*self.history.lock().unwrap() = match document.history {
+				Some(mut history) => {
+					history.set_limits(
+						preferences.history_depth,
+						preferences.history_memory_bytes(),
+					);
+					history
+				}
+				None => preferences.history(),
+			};
+			*self.image_buffer.lock().unwrap() = image_buffer;
+			*self.autosave_pending.lock().unwrap() = true;
+		}
+		self.reload_page_pack();
+		self.reload_layer_pack();
+		self.history_panel.invalidate();
+		self.refresh_history_panel();
+		self.page_cache.lock().unwrap().invalidate();
+		self.area.queue_draw();
+		if !warnings.is_empty() {
+			self.show_warning("Parts of the notebook could not be loaded.", &warnings);
+		}
+		Ok(())
+	}
+
+	/// Places the image file at `path_puf` on the active layer of `self.current_page`.
+	///
+	/// The image is only added if the file could be read and decoded and the active layer is visible and unlocked.
+	fn import_image(&self, path_puf: &PathBuf) -> Result<()> {
+		let data = std::fs::read(path_puf)?;
+		let initial_position = (20.0, 20.0);
+		let image = hnote::model::Image::new(path_puf.clone(), data, initial_position);
+		let buffered_image = BufferedImage::from_image(&image)?;
+		let hash = image.hash.clone();
+		let current_page = *self.current_page.lock().unwrap();
+		let operation = {
+			let mut pages = self.pages.lock().unwrap();
+			let layer = pages[current_page].active_layer_index();
+			let index = match pages[current_page].editable_layer_mut() {
+				Some(editable_layer) => editable_layer.images.len(),
+				None => {
+					return Err(Error::Format(
+						"The active layer is hidden or locked.".to_string(),
+					))
+				}
+			};
+			let operation = Operation::AddImage {
+				page: current_page,
+				layer,
+				index,
+				image,
+			};
+			operation.apply(&mut pages);
+			operation
+		};
+		self.image_buffer
+			.lock()
+			.unwrap()
+			.insert(hash, buffered_image);
+		self.record(operation, current_page);
+		self.page_cache.lock().unwrap().invalidate();
+		self.area.queue_draw();
+		Ok(())
+	}
+
+	/// Adds `operation`, which has already been applied to `self.pages` while `viewed_page` was shown, to `self.history`.
+	fn record(&self, operation: Operation, viewed_page: usize) {
+		self.history.lock().unwrap().push(operation, viewed_page);
+		*self.autosave_pending.lock().unwrap() = true;
+		self.refresh_history_panel();
+	}
+
+	/// Lists the current `self.history` in `self.history_panel`.
+	fn refresh_history_panel(&self) {
+		let pages = self.pages.lock().unwrap();
+		let history = self.history.lock().unwrap();
+		self.history_panel
+			.refresh(&history, &pages, self.canvas_size());
+	}
+
+	/// Applies the page or layer structure `operation` to `self.pages`, records it and shows the page that it changed.
+	fn apply_page_operation(&self, operation: Operation) {
+		let viewed_page = *self.current_page.lock().unwrap();
+		let changed_page = operation.apply(&mut self.pages.lock().unwrap());
+		self.record(operation, viewed_page);
+		self.show_page(changed_page);
+	}
+
+	/// Shows the page at `page_index` and rebuilds the page buttons and the layer panel, e.g. after the page structure changed.
+	fn show_page(&self, page_index: usize) {
+		*self.current_page.lock().unwrap() = page_index;
+		self.reload_page_pack();
+		self.reload_layer_pack();
+		self.reload_image_buffer();
+		self.page_cache.lock().unwrap().invalidate();
+		self.area.queue_draw();
+	}
+
+	/// Decodes the images of `self.current_page` that are not in `self.image_buffer` yet.
+	///
+	/// Needed whenever the images of the page change outside of the canvas, e.g. by undo and redo.
+	fn reload_image_buffer(&self) {
+		let reloaded = {
+			let pages = self.pages.lock().unwrap();
+			let current_page = self.current_page.lock().unwrap();
+			let mut image_buffer = self.image_buffer.lock().unwrap();
+			reload_image_buffer(&pages[*current_page], &mut image_buffer)
+		};
+		if let Err(error) = reloaded {
+			self.show_error("Could not load the images of the page.", &error);
+		}
+	}
+
+	/// Shows a `gtk::MessageDialog` on top of `self.window` that explains the `error`.
+	fn show_error(&self, message: &str, error: &Error) {
+		show_error_dialog(Some(self.window.upcast_ref()), message, error);
+	}
+
+	/// Shows a `gtk::MessageDialog` on top of `self.window` with `message` and the `details` of a problem that did not stop an action.
+	fn show_warning(&self, message: &str, details: &str) {
+		let dialog = MessageDialog::new(
+			Some(&self.window),
+			DialogFlags::DESTROY_WITH_PARENT | DialogFlags::MODAL,
+			MessageType::Warning,
+			ButtonsType::Close,
+			message,
+		);
+		dialog.set_property_secondary_text(Some(details));
+		dialog.connect_response(|dialog, _| dialog.close());
+		dialog.show_all();
+	}
+
+	/// Connects a `gtk::FileChooserNative` instance with an `action`.
+	fn connect_file_dialog(
+		&self,
+		file_chooser_action: FileChooserAction,
+		action: Heap<dyn Fn(PathBuf)>,
+	) {
+		self.connect_filtered_file_dialog(file_chooser_action, &[], action);
+	}
+
+	/// Connects a `gtk::FileChooserNative` instance that offers `filters` with an `action`.
+	fn connect_filtered_file_dialog(
+		&self,
+		file_chooser_action: FileChooserAction,
+		filters: &[FileFilter],
+		action: Heap<dyn Fn(PathBuf)>,
+	) {
+		let file_chooser = FileChooserNativeBuilder::new()
+			.title("Choose file...")
+			.action(file_chooser_action)
+			.local_only(true)
+			.transient_for(&self.window)
+			.modal(true)
+			.build();
+		for filter in filters.iter() {
+			file_chooser.add_filter(filter);
+		}
+		file_chooser.connect_response(
+			clone!(@strong self as this => move |file_chooser, response| {
+				if response == ResponseType::Accept {
+					match file_chooser.get_filename() {
+						Some(filename) => action(filename),
+						None => this.show_error(
+							"Could not use the chosen file.",
+							&Error::Format("Only local files are supported.".to_string()),
+						),
+					}
+				}
+				file_chooser.destroy();
+			}),
+		);
+		file_chooser.run();
+	}
+
+	/// Creates `gtk::FileFilter`s for all image formats that gdk-pixbuf can load.
+	///
+	/// The first filter accepts every supported format, it is followed by one filter per format.
+	fn image_filters() -> Vec<FileFilter> {
+		let formats = Pixbuf::get_formats();
+		let all_images = FileFilter::new();
+		all_images.set_name(Some("All images"));
+		let mut filters = vec![all_images.clone()];
+		for format in formats.iter() {
+			let filter = FileFilter::new();
+			filter.set_name(format.get_description().as_deref());
+			for mime_type in format.get_mime_types() {
+				all_images.add_mime_type(&mime_type);
+				filter.add_mime_type(&mime_type);
+			}
+			filters.push(filter);
+		}
+		filters
+	}
+
+	/// Connects a `gtk::FileChooserNative` instance with an `action`.
+	/// If `self.current_path` is not already specified, the `action` is executed.
+	fn connect_path_or_file_dialog(
+		&self,
+		file_chooser_action: FileChooserAction,
+		action: Heap<dyn Fn(PathBuf)>,
+	) {
+		let current_path = self.current_path.lock().unwrap().clone();
+		match current_path {
+			Some(current_path) => action(current_path.clone()),
+			None => {
+				self.connect_file_dialog(file_chooser_action, action);
+			}
+		}
+	}
+
+	/// Reloads `self.application_layout.page_pack` depending on `self`.
+	fn reload_page_pack(&self) {
+		for button in self.application_layout.page_pack.get_children() {
+			self.application_layout.page_pack.remove(&button);
+		}
+		let page_count = self.pages.lock().unwrap().len();
+		for _ in 0..page_count {
+			connect_page_button(
+				Rc::clone(&self.pages),
+				Rc::clone(&self.current_page),
+				Rc::clone(&self.image_buffer),
+				self.area.clone(),
+				&self.application_layout.page_pack,
+			)
+			.connect_clicked(clone!(@strong self as this => move |_| {
+				this.reload_layer_pack();
+			}));
+		}
+		self.add_page();
+		self.remove_page();
+		self.move_page();
+		self.application_layout.page_pack.show_all();
+	}
+
+	/// Reloads `self.application_layout.layer_pack` with the layers of `self.current_page`, the top layer first.
+	///
+	/// Every layer can be selected as active layer, renamed, hidden, locked, faded and moved.
+	/// All changes except selecting the active layer are recorded in `self.history`.
+	fn reload_layer_pack(&self) {
+		let layer_pack = &self.application_layout.layer_pack;
+		for child in layer_pack.get_children() {
+			layer_pack.remove(&child);
+		}
+		let (layers, active_layer) = {
+			let pages = self.pages.lock().unwrap();
+			let page = &pages[*self.current_page.lock().unwrap()];
+			let layers: Vec<LayerProperties> = page
+				.layers
+				.iter()
+				.map(|layer| layer.properties.clone())
+				.collect();
+			(layers, page.active_layer_index())
+		};
+		layer_pack.pack_start(&Label::new(Some("Layers")), false, false, 0);
+		let mut group: Option<RadioButton> = None;
+		for (index, properties) in layers.iter().enumerate().rev() {
+			let select = RadioButton::new();
+			select.join_group(group.as_ref());
+			select.set_active(index == active_layer);
+			select.connect_toggled(clone!(@strong self as this => move |select| {
+				if select.get_active() {
+					let current_page = *this.current_page.lock().unwrap();
+					this.pages.lock().unwrap()[current_page].active_layer = index;
+					*this.autosave_pending.lock().unwrap() = true;
+				}
+			}));
+			group.get_or_insert_with(|| select.clone());
+			layer_pack.pack_start(
+				&self.layer_row(&select, index, properties, layers.len()),
+				false,
+				false,
+				0,
+			);
+		}
+
+		let layer_buttons = Box::new(Orientation::Horizontal, 0);
+		let add_layer = Button::with_label("+");
+		add_layer.connect_clicked(clone!(@strong self as this => move |_| {
+			let operation = {
+				let pages = this.pages.lock().unwrap();
+				let current_page = *this.current_page.lock().unwrap();
+				let page = &pages[current_page];
+				Operation::AddLayer {
+					page: current_page,
+					index: page.active_layer_index() + 1,
+					layer: Layer::new(&format!("Layer {}", page.layers.len() + 1)),
+				}
+			};
+			this.apply_page_operation(operation);
+		}));
+		layer_buttons.pack_start(&add_layer, true, true, 0);
+		let remove_layer = Button::with_label("-");
+		remove_layer.set_sensitive(layers.len() > 1);
+		remove_layer.connect_clicked(clone!(@strong self as this => move |_| {
+			let operation = {
+				let pages = this.pages.lock().unwrap();
+				let current_page = *this.current_page.lock().unwrap();
+				let page = &pages[current_page];
+				let index = page.active_layer_index();
+				Operation::RemoveLayer {
+					page: current_page,
+					index,
+					layer: page.layers[index].clone(),
+				}
+			};
+			this.apply_page_operation(operation);
+		}));
+		layer_buttons.pack_start(&remove_layer, true, true, 0);
+		layer_pack.pack_start(&layer_buttons, false, false, 0);
+		layer_pack.show_all();
+	}
+
+	/// Composes the controls of the layer at `index` of `self.current_page` with the active layer button `select`.
+	///
+	/// `layer_count` is the number of layers of the page, the top layer can not be moved up.
+	fn layer_row(
+		&self,
+		select: &RadioButton,
+		index: usize,
+		properties: &LayerProperties,
+		layer_count: usize,
+	) -> Box {
+		let name = Entry::new();
+		name.set_text(&properties.name);
+		name.set_width_chars(10);
+		name.connect_activate(clone!(@strong self as this => move |name| {
+			let name = name.get_text().to_string();
+			this.change_layer(index, |properties| properties.name = name);
+		}));
+		let header = Box::new(Orientation::Horizontal, 0);
+		header.pack_start(select, false, false, 0);
+		header.pack_start(&name, true, true, 0);
+
+		let visible = CheckButton::with_label("Visible");
+		visible.set_active(properties.visible);
+		visible.connect_toggled(clone!(@strong self as this => move |visible| {
+			let visible = visible.get_active();
+			this.change_layer(index, |properties| properties.visible = visible);
+		}));
+		let locked = CheckButton::with_label("Locked");
+		locked.set_active(properties.locked);
+		locked.connect_toggled(clone!(@strong self as this => move |locked| {
+			let locked = locked.get_active();
+			this.change_layer(index, |properties| properties.locked = locked);
+		}));
+		let opacity = SpinButton::with_range(0.0, 1.0, 0.1);
+		opacity.set_value(properties.opacity);
+		opacity.connect_value_changed(clone!(@strong self as this => move |opacity| {
+			let opacity = opacity.get_value();
+			this.change_layer(index, |properties| properties.opacity = opacity);
+		}));
+		let move_up = Button::with_label("↑");
+		move_up.set_sensitive(index + 1 < layer_count);
+		move_up.connect_clicked(clone!(@strong self as this => move |_| {
+			this.move_layer(index, index + 1);
+		}));
+		let move_down = Button::with_label("↓");
+		move_down.set_sensitive(index > 0);
+		move_down.connect_clicked(clone!(@strong self as this => move |_| {
+			this.move_layer(index, index - 1);
+		}));
+		let controls = Box::new(Orientation::Horizontal, 0);
+		controls.pack_start(&visible, false, false, 0);
+		controls.pack_start(&locked, false, false, 0);
+		controls.pack_start(&opacity, false, false, 0);
+		controls.pack_start(&move_up, false, false, 0);
+		controls.pack_start(&move_down, false, false, 0);
+
+		let row = Box::new(Orientation::Vertical, 0);
+		row.pack_start(&header, false, false, 0);
+		row.pack_start(&controls, false, false, 0);
+		row
+	}
+
+	/// Changes the properties of the layer at `index` of `self.current_page` with `change`.
+	///
+	/// The change is recorded in `self.history` unless the properties stay the same,
+	/// consecutive changes of the same properties of the layer are recorded as one entry.
+	/// The layer panel already shows the change, so it is not rebuilt.
+	fn change_layer(&self, index: usize, change: impl FnOnce(&mut LayerProperties)) {
+		let (operation, current_page) = {
+			let mut pages = self.pages.lock().unwrap();
+			let current_page = *self.current_page.lock().unwrap();
+			let properties = &mut pages[current_page].layers[index].properties;
+			let before = properties.clone();
+			change(properties);
+			if *properties == before {
+				return;
+			}
+			let operation = Operation::ChangeLayer {
+				page: current_page,
+				index,
+				before,
+				after: properties.clone(),
+			};
+			(operation, current_page)
+		};
+		self.history
+			.lock()
+			.unwrap()
+			.push_merged(operation, current_page);
+		*self.autosave_pending.lock().unwrap() = true;
+		self.refresh_history_panel();
+		self.page_cache.lock().unwrap().invalidate();
+		self.area.queue_draw();
+	}
+
+	/// Moves the layer at `from` of `self.current_page` to `to` and records it in `self.history`.
+	fn move_layer(&self, from: usize, to: usize) {
+		let page = *self.current_page.lock().unwrap();
+		self.apply_page_operation(Operation::MoveLayer { page, from, to });
+	}
+
+	/// Starts the crash recovery session of `self.window` and the periodic autosave.
+	///
+	/// If a window of an earlier session was not shut down cleanly, the user is offered to restore its recovered notebook.
+	/// The recovery file is removed when the window is closed or the application shuts down.
+	fn recover(&self, gtk_application: &gtk::Application) {
+		gtk_application.connect_shutdown(clone!(@strong self.recovery as recovery => move |_| {
+			recovery.end_session();
+		}));
+		self.window
+			.connect_destroy(clone!(@strong self.recovery as recovery, @strong self.autosave_pending as autosave_pending => move |_| {
+				*autosave_pending.lock().unwrap() = false;
+				recovery.end_session();
+			}));
+		match self.recovery.start_session() {
+			Ok(true) => self.offer_recovery(),
+			Ok(false) => {}
+			Err(error) => return self.show_error("Autosave is not available.", &error),
+		}
+		self.autosave();
+	}
+
+	/// Asks the user whether the recovered notebook should replace the empty notebook.
+	///
+	/// The recovery file is discarded if the user declines.
+	fn offer_recovery(&self) {
+		let dialog = MessageDialog::new(
+			Some(&self.window),
+			DialogFlags::DESTROY_WITH_PARENT | DialogFlags::MODAL,
+			MessageType::Question,
+			ButtonsType::YesNo,
+			"The editor was not shut down properly.",
+		);
+		dialog.set_property_secondary_text(Some("Do you want to restore the recovered notebook?"));
+		dialog.connect_response(clone!(@strong self as this => move |dialog, response| {
+			if response == ResponseType::Yes {
+				if let Err(error) = this.recovery.read().and_then(|document| this.load_document(document)) {
+					this.show_error("Could not restore the recovered notebook.", &error);
+				}
+			} else {
+				this.recovery.discard();
+			}
+			dialog.close();
+		}));
+		dialog.show_all();
+	}
+
+	/// Periodically writes `self.pages` to the recovery file.
+	///
+	/// The recovery file is only written if an operation was recorded since the last autosave and no stroke is in progress.
+	/// A failed autosave is shown once, further failures are only shown after an autosave succeeded again.
+	fn autosave(&self) {
+		let mut failed = false;
+		timeout_add_seconds_local(
+			AUTOSAVE_INTERVAL,
+			clone!(@strong self as this => move || {
+				let mut autosave_pending = this.autosave_pending.lock().unwrap();
+				if *autosave_pending && !*this.drawing_information.pen_is_active.lock().unwrap() {
+					match this.recovery.write(&this.document()) {
+						Ok(()) => {
+							*autosave_pending = false;
+							failed = false;
+						}
+						Err(error) if !failed => {
+							failed = true;
+							this.show_error("Autosave failed, the notebook can not be recovered after a crash.", &error);
+						}
+						Err(_) => {}
+					}
+				}
+				Continue(true)
+			}),
+		);
+	}
+
+	/// Implements command based version control.
+	///
+	/// After each interaction with a `DrawTool` the operation that the tool reports is recorded in `self.history`.
+	fn undo_redo(&self) {
+		self.area
+			.connect_button_release_event(clone!(@strong self as this => move |_, e| {
+				if this.input_action(e.get_source_device()) != InputAction::Draw {
+					return Inhibit(false);
+				}
+				let stroke_start = this.stroke_start.lock().unwrap().take();
+				if let Some((page_index, layer_index, draw_tool)) = stroke_start {
+					let operation = {
+						let mut pages = this.pages.lock().unwrap();
+						let tool = this.drawing_information.tools.get(&draw_tool);
+						let layer = pages.get_mut(page_index).and_then(|page| page.layers.get_mut(layer_index));
+						layer.and_then(|layer| tool.lock().unwrap().finish(page_index, layer_index, layer))
+					};
+					if let Some(operation) = operation {
+						this.record(operation, page_index);
+					}
+					this.page_cache.lock().unwrap().finish_stroke();
+					this.area.queue_draw();
+				}
+				Inhibit(false)
+			}));
+
+		let undo = Button::with_label("Undo");
+		undo.connect_clicked(clone!(@strong self as this => move |_| {
+			this.step_history(History::undo);
+		}));
+		self.application_layout
+			.tool_pack
+			.pack_start(&undo, false, false, 0);
+
+		let redo = Button::with_label("Redo");
+		redo.connect_clicked(clone!(@strong self as this => move |_| {
+			this.step_history(History::redo);
+		}));
+		self.application_layout
+			.tool_pack
+			.pack_start(&redo, false, false, 0);
+
+		let history = Button::with_label("History");
+		history.connect_clicked(clone!(@strong self as this => move |_| {
+			this.history_panel.show();
+			this.refresh_history_panel();
+		}));
+		self.application_layout
+			.tool_pack
+			.pack_start(&history, false, false, 0);
+		self.history_panel
+			.connect_jump(clone!(@strong self as this => move |target| {
+				this.jump_history(target);
+			}));
+	}
+
+	/// Undoes, redoes or jumps through the history with `step`.
+	///
+	/// Undoing shows the page that was viewed before the operation, redoing shows the page that the operation changed.
+	fn step_history(&self, step: impl FnOnce(&mut History, &mut Vec<Page>) -> Option<usize>) {
+		let page_index = {
+			let mut pages = self.pages.lock().unwrap();
+			let page_index = match step(&mut self.history.lock().unwrap(), &mut pages) {
+				Some(page_index) => page_index,
+				None => return,
+			};
+			*self.autosave_pending.lock().unwrap() = true;
+			page_index.min(pages.len() - 1)
+		};
+		self.show_page(page_index);
+		self.refresh_history_panel();
+	}
+
+	/// Brings the pages into the state after the history entry `target`, `None` being the oldest stored state.
+	///
+	/// Recording an operation afterwards starts a new branch, so no entry of the history is lost.
+	fn jump_history(&self, target: Option<EntryId>) {
+		self.step_history(|history, pages| history.jump(pages, target));
+	}
+
+	/// Pages can be added.
+	///
+	/// Connects `gtk::Button` to append a page on click and show it.
+	/// Adding a page is recorded in `self.history`.
+	fn add_page(&self) {
+		let add_page = Button::with_label("+");
+		add_page.connect_clicked(clone!(@strong self as this => move |_| {
+			let index = this.pages.lock().unwrap().len();
+			this.apply_page_operation(Operation::AddPage {
+				index,
+				page: Page::new(),
+			});
+		}));
+		self.application_layout
+			.page_pack
+			.pack_start(&add_page, false, false, 0);
+	}
+
+	/// Pages can be removed.
+	///
+	/// Connects `gtk::Button` to remove the `self.current_page` on click.
+	/// Afterwards the following page is shown, or the previous one if the last page was removed.
+	/// Removing a page is recorded in `self.history`, undoing it restores the page with its contents.
+	fn remove_page(&self) {
+		let remove_page = Button::with_label("-");
+		remove_page.connect_clicked(clone!(@strong self as this => move |_| {
+			let operation = {
+				let pages = this.pages.lock().unwrap();
+				let current_page = *this.current_page.lock().unwrap();
+				if pages.len() > 1 {
+					Some(Operation::RemovePage {
+						index: current_page,
+						page: pages[current_page].clone(),
+					})
+				} else {
+					None
+				}
+			};
+			if let Some(operation) = operation {
+				this.apply_page_operation(operation);
+			}
+		}));
+		self.application_layout
+			.page_pack
+			.pack_end(&remove_page, false, false, 0);
+	}
+
+	/// Pages can be moved.
+	///
+	/// Connects buttons to move the `self.current_page` up and down.
+	/// Moving a page is recorded in `self.history`.
+	fn move_page(&self) {
+		let move_up = Button::with_label("↓");
+		move_up.connect_clicked(clone!(@strong self as this => move |_| {
+			let page_count = this.pages.lock().unwrap().len();
+			let current_page = *this.current_page.lock().unwrap();
+			if current_page < page_count - 1 {
+				this.apply_page_operation(Operation::MovePage {
+					from: current_page,
+					to: current_page + 1,
+				});
+			}
+		}));
+		self.application_layout
+			.page_pack
+			.pack_end(&move_up, false, false, 0);
+
+		let move_down = Button::with_label("↑");
+		move_down.connect_clicked(clone!(@strong self as this => move |_| {
+			let current_page = *this.current_page.lock().unwrap();
+			if current_page > 0 {
+				this.apply_page_operation(Operation::MovePage {
+					from: current_page,
+					to: current_page - 1,
+				});
+			}
+		}));
+		self.application_layout
+			.page_pack
+			.pack_end(&move_down, false, false, 0);
+	}
+
+	/// Ensures correct interaction based on `self.drawing_information.current_draw_tool`.
+	///
+	/// Instantiates all `DrawTool` implementations.
+	///
+	/// Invokes `self.color_widget()`.
+	fn manage_drawing_modes(&self) {
+		self.color_widget();
+
+		let current_draw_tool = &self.drawing_information.current_draw_tool;
+		let tool_pack = &self.application_layout.tool_pack;
+		tool_button(
+			"Pen",
+			CurrentDrawTool::Pencil,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+		tool_button(
+			"Eraser",
+			CurrentDrawTool::Eraser,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+		tool_button(
+			"Line Eraser",
+			CurrentDrawTool::LineEraser,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+		tool_button(
+			"Line Tool",
+			CurrentDrawTool::LineTool,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+		tool_button(
+			"Drag",
+			CurrentDrawTool::Drag,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+		let rectangle_selection = Rc::clone(&self.drawing_information.tools.rectangle_selection);
+		tool_button(
+			"Rect Selection",
+			CurrentDrawTool::RectangleSelection,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		)
+		.connect_clicked(clone!(@strong rectangle_selection => move |_| {
+			rectangle_selection.lock().unwrap().reset();
+		}));
+		self.rectangle_selection_overlay(Rc::clone(&rectangle_selection));
+		tool_button(
+			"Clear",
+			CurrentDrawTool::Clear,
+			Rc::clone(current_draw_tool),
+			&self.area,
+			tool_pack,
+		);
+
+		self.area.connect_motion_notify_event(clone!(@strong self as this => move |_, e| {
+			match this.input_action(e.get_source_device()) {
+				InputAction::Draw => {}
+				InputAction::Pan => {
+					this.pan_canvas(e.get_root());
+					return Inhibit(false);
+				}
+				InputAction::Ignore => return Inhibit(false),
+			}
+			this.follow_input_source(e.get_source_device());
+			let current_draw_tool = this.drawing_information.current_draw_tool.lock().unwrap();
+			let active_draw_tool = this.drawing_information.tools.get(&current_draw_tool);
+			// The pressure of a stylus scales the size of drawn points, devices without pressure and all other tools use the slider value.
+			let pen_size = *this.drawing_information.pen_size.lock().unwrap();
+			let rgba = *this.drawing_information.rgba.lock().unwrap();
+			let (pen_size, rgba) = if matches!(*current_draw_tool, CurrentDrawTool::Pencil | CurrentDrawTool::LineTool) {
+				let pressure_curve = this.preferences.lock().unwrap().pressure_curve;
+				pressure_curve.apply(e.get_axis(AxisUse::Pressure), pen_size, rgba)
+			} else {
+				(pen_size, rgba)
+			};
+			let pen_is_active = this.drawing_information.pen_is_active.lock().unwrap();
+			let viewport = *this.viewport.lock().unwrap();
+			let previous_rectangle = rectangle_selection.lock().unwrap().rectangle();
+			let (changed, live_stroke_damage) = {
+				let mut pages = this.pages.lock().unwrap();
+				let current_page = this.current_page.lock().unwrap();
+				let page_cache = this.page_cache.lock().unwrap();
+				let before = page_cache.live_stroke_damage(*current_page, &pages[*current_page]);
+				let changed = match pages[*current_page].editable_layer_mut() {
+					Some(layer) => {
+						let image_sizes = image_sizes(layer, &this.image_buffer.lock().unwrap());
+						active_draw_tool.lock().unwrap().manipulate(layer, &image_sizes, viewport.to_page(e.get_position()), pen_size, *pen_is_active, rgba)
+					}
+					None => false,
+				};
+				let after = page_cache.live_stroke_damage(*current_page, &pages[*current_page]);
+				(changed, before.into_iter().chain(after).reduce(page_cache::union))
+			};
+			// Pencil and LineTool only change the live stroke, every other tool may change the whole page while the pen is active.
+			if *pen_is_active {
+				if matches!(*current_draw_tool, CurrentDrawTool::Pencil | CurrentDrawTool::LineTool) {
+					if let Some(damage) = live_stroke_damage {
+						page_cache::queue_draw_rectangle(&this.area, viewport.rectangle_to_widget(damage));
+					}
+				} else if changed {
+					this.page_cache.lock().unwrap().invalidate();
+					this.area.queue_draw();
+				} else if *current_draw_tool == CurrentDrawTool::RectangleSelection {
+					let rectangle = rectangle_selection.lock().unwrap().rectangle();
+					let damage = page_cache::union(viewport.rectangle_to_widget(previous_rectangle), viewport.rectangle_to_widget(rectangle));
+					let padding = SELECTION_LINE_WIDTH / 2.0;
+					page_cache::queue_draw_rectangle(&this.area, [damage[0] - padding, damage[1] - padding, damage[2] + padding, damage[3] + padding]);
+				}
+			}
+			Inhibit(false)
+		}));
+	}
+
+	/// Draws the rectangle of `rectangle_selection` on top of the canvas while the tool is active.
+	fn rectangle_selection_overlay(&self, rectangle_selection: Rc<Mutex<RectangleSelection>>) {
+		let line_matrix = [(0, 1), (2, 1), (2, 3), (0, 3), (0, 1)];
+		self.area.connect_draw(
+			clone!(@strong self.drawing_information.current_draw_tool as current_draw_tool, @strong self.viewport as viewport => move |_, cr| {
+				if *current_draw_tool.lock().unwrap() == CurrentDrawTool::RectangleSelection {
+					let rectangle = rectangle_selection.lock().unwrap().rectangle();
+					let viewport = *viewport.lock().unwrap();
+					cr.save();
+					viewport.apply(cr);
+					cr.set_source_rgba(0.0, 0.0, 0.0, 0.5);
+					for line in line_matrix.iter() {
+						cr.set_line_width(SELECTION_LINE_WIDTH / viewport.scale);
+						cr.line_to(rectangle[line.0], rectangle[line.1]);
+					}
+					cr.stroke();
+					cr.restore();
+				}
+				Inhibit(false)
+			}),
+		);
+	}
+
+	/// Composes `color_widget` with color selection dialog and predefined colors.
+	///
+	/// Adds `color_widget` to `self.application_layout.tool_pack`.
+	fn color_widget(&self) {
+		let color_widget = Box::new(Orientation::Horizontal, 0);
+
+		let predefined_colors = [
+			[0.0, 0.0, 0.0, 1.0],
+			[1.0, 1.0, 1.0, 1.0],
+			[1.0, 0.0, 0.0, 1.0],
+			[0.0, 1.0, 0.0, 1.0],
+			[0.0, 0.0, 1.0, 1.0],
+			[1.0, 1.0, 0.0, 1.0],
+			[0.0, 1.0, 1.0, 1.0],
+			[1.0, 0.0, 1.0, 1.0],
+		];
+		for predefined_rgba in predefined_colors.iter() {
+			let select_color = Button::new();
+			select_color.connect_clicked(
+				clone!(@strong self.drawing_information.rgba as rgba, @strong predefined_rgba => move |_| {
+					*rgba.lock().unwrap() = predefined_rgba;
+				}),
+			);
+			let predefined_rgba = Some(RGBA {
+				red: predefined_rgba[0],
+				green: predefined_rgba[1],
+				blue: predefined_rgba[2],
+				alpha: predefined_rgba[3],
+			});
+			let color_preview = Label::new(None);
+			color_preview.set_size_request(20, 20);
+			color_preview.override_background_color(StateFlags::NORMAL, predefined_rgba.as_ref());
+			select_color.set_image(Some(&color_preview));
+			color_widget.pack_start(&select_color, false, false, 0);
+		}
+
+		let color_dialog = Button::with_label("Colors");
+		color_dialog.connect_clicked(clone!(@strong self as this => move |_| {
+			let rgba = &this.drawing_information.rgba;
+			let dialog = Dialog::with_buttons(
+				Some("Colors"),
+				Some(&this.window.clone()),
+				DialogFlags::DESTROY_WITH_PARENT,
+				&[("Close", ResponseType::Close)],
+			);
+			dialog.set_default_response(ResponseType::Close);
+			dialog.connect_response(|dialog, _| dialog.close());
+
+			let scales = [
+				Scale::with_range(Orientation::Horizontal, 0.01, 1.0, 0.01),
+				Scale::with_range(Orientation::Horizontal, 0.01, 1.0, 0.01),
+				Scale::with_range(Orientation::Horizontal, 0.01, 1.0, 0.01),
+				Scale::with_range(Orientation::Horizontal, 0.01, 1.0, 0.01),
+			];
+			let content_area = dialog.get_content_area();
+			let color_preview = Label::new(None);
+			content_area.pack_start(&color_preview, false, false, 0);
+			for (i, scale) in scales.iter().enumerate() {
+				scale.set_value(rgba.lock().unwrap()[i]);
+				content_area.add(scale);
+					scale.connect_change_value(clone!(@strong color_preview, @strong rgba => move |_, _, v| {
+						let mut rgba = rgba.lock().unwrap();
+						rgba[i] = v;
+						let rgba = Some(RGBA {red: rgba[0], green: rgba[1], blue: rgba[2], alpha: rgba[3]});
+						color_preview.override_background_color(StateFlags::NORMAL, rgba.as_ref());
+						Inhibit(false)
+					}));
+			}
+			dialog.show_all();
+		}));
+		color_widget.pack_start(&color_dialog, false, false, 0);
+
+		self.application_layout
+			.tool_pack
+			.pack_start(&color_widget, false, false, 0);
+	}
+
+	/// Displays the position pointer on the canvas in the current color.
+	fn position_pointer(&self) {
+		self.area.connect_motion_notify_event(clone!(@strong self as this => move |_, e| {
+			let previous_position = this.drawing_information.cursor_position.lock().unwrap().replace(e.get_position());
+			if let Some(previous_position) = previous_position {
+				this.queue_draw_pointer(previous_position);
+			}
+			this.queue_draw_pointer(e.get_position());
+			Inhibit(false)
+		}));
+
+		self.area
+			.connect_leave_notify_event(clone!(@strong self as this => move |_, _| {
+				let previous_position = this.drawing_information.cursor_position.lock().unwrap().take();
+				if let Some(previous_position) = previous_position {
+					this.queue_draw_pointer(previous_position);
+				}
+				Inhibit(false)
+			}));
+
+		self.area
+			.connect_draw(clone!(@strong self as this => move |_, cr| {
+				let cursor_position = this.drawing_information.cursor_position.lock().unwrap();
+				if cursor_position.is_some() {
+					let pen_size = this.drawing_information.pen_size.lock().unwrap();
+					let scale = this.viewport.lock().unwrap().scale;
+					let rgba = *this.drawing_information.rgba.lock().unwrap();
+					cr.set_source_rgba(
+						rgba[0],
+						rgba[1],
+						rgba[2],
+						rgba[3],
+					);
+					cr.set_line_width(5.0);
+					cr.arc(cursor_position.unwrap().0, cursor_position.unwrap().1, *pen_size * scale / 2.0, 0.0, PI * 2.0);
+					cr.stroke();
+				}
+				Inhibit(false)
+			}));
+	}
+
+	/// Schedules a redraw of the area that the position pointer covers at the widget position `position`.
+	fn queue_draw_pointer(&self, position: (f64, f64)) {
+		let scale = self.viewport.lock().unwrap().scale;
+		let radius = *self.drawing_information.pen_size.lock().unwrap() * scale / 2.0 + 5.0;
+		page_cache::queue_draw_rectangle(
+			&self.area,
+			[
+				position.0 - radius,
+				position.1 - radius,
+				position.0 + radius,
+				position.1 + radius,
+			],
+		);
+	}
+
+	/// Opens a dialog to export every page as .png file.
+	///
+	/// The user chooses the scale of the canvas size, the output directory and the file name pattern.
+	fn export_png(&self) {
+		let dialog = Dialog::with_buttons(
+			Some("Export as PNG"),
+			Some(&self.window),
+			DialogFlags::DESTROY_WITH_PARENT | DialogFlags::MODAL,
+			&[
+				("Cancel", ResponseType::Cancel),
+				("Export", ResponseType::Accept),
+			],
+		);
+		dialog.set_default_response(ResponseType::Accept);
+		let content_area = dialog.get_content_area();
+
+		let scale_label = Label::new(Some("Scale"));
+		let scale = SpinButton::with_range(0.1, 10.0, 0.1);
+		scale.set_digits(1);
+		scale.set_value(1.0);
+		let directory_label = Label::new(Some("Directory"));
+		let directory =
+			FileChooserButton::new("Choose directory...", FileChooserAction::SelectFolder);
+		let pattern_label = Label::new(Some("File name pattern ({n} is the page number)"));
+		let pattern = Entry::new();
+		pattern.set_text("page-{n}.png");
+		content_area.pack_start(&scale_label, false, false, 0);
+		content_area.pack_start(&scale, false, false, 0);
+		content_area.pack_start(&directory_label, false, false, 0);
+		content_area.pack_start(&directory, false, false, 0);
+		content_area.pack_start(&pattern_label, false, false, 0);
+		content_area.pack_start(&pattern, false, false, 0);
+		dialog.show_all();
+
+		dialog.connect_response(clone!(@strong self as this, @strong scale, @strong directory, @strong pattern => move |dialog, response| {
+			if response == ResponseType::Accept {
+				let pages = this.pages.lock().unwrap().clone();
+				let exported = match directory.get_filename() {
+					Some(directory) => render::export_pngs(&directory, pattern.get_text().as_str(), &pages, this.canvas_size(), scale.get_value()),
+					None => Err(Error::Format("No directory was chosen.".to_string())),
+				};
+				if let Err(error) = exported {
+					this.show_error("Could not export the pages.", &error);
+				}
+			}
+			dialog.close();
+		}));
+	}
+
+	/// Connects a `gtk::FileChooserNative` instance to export all pages to the chosen .pdf file.
+	fn export_pdf(&self) {
+		self.connect_file_dialog(
+			FileChooserAction::Save,
+			Heap::new(clone!(@strong self as this => move |current_path| {
+				let pages = this.pages.lock().unwrap().clone();
+				if let Err(error) = render::export_pdf(&current_path, &pages, this.canvas_size()) {
+					this.show_error(&format!("Could not export {}.", current_path.display()), &error);
+				}
+			})),
+		);
+	}
+
+	/// Connects a `gtk::FileChooserNative` instance to export pages to the chosen .svg file.
+	///
+	/// Exports the `self.current_page`, or every page as numbered files if `all_pages` is set.
+	fn export_svg(&self, all_pages: bool) {
+		self.connect_file_dialog(
+			FileChooserAction::Save,
+			Heap::new(clone!(@strong self as this => move |current_path| {
+				let pages = this.pages.lock().unwrap().clone();
+				let current_page = *this.current_page.lock().unwrap();
+				let exported = if all_pages {
+					svg::export_svgs(&current_path, &pages, this.canvas_size())
+				} else {
+					svg::export_svg(&current_path, &pages[current_page], this.canvas_size())
+				};
+				if let Err(error) = exported {
+					this.show_error(&format!("Could not export {}.", current_path.display()), &error);
+				}
+			})),
+		);
+	}
+
+	/// Size of the canvas as set by "Change canvas size".
+	fn canvas_size(&self) -> (f64, f64) {
+		*self.canvas_size.lock().unwrap()
+	}
+}
@@ -0,0 +1,56 @@
+use crate::editor::error::{Error, Result};
+use crate::editor::render;
+use crate::editor::svg;
+use hnote::bundle;
+use hnote::render_options::{OutputFormat, RenderOptions, RENDER_USAGE};
+use std::fs;
+
+/// Runs the `render` subcommand with the `arguments` that follow it.
+///
+/// Pages are drawn with the same logic as the canvas, no display connection is required.
+pub fn render(arguments: &[String]) -> Result<()> {
+	if arguments
+		.iter()
+		.any(|argument| argument == "-h" || argument == "--help")
+	{
+		println!("{}", RENDER_USAGE);
+		return Ok(());
+	}
+	let options = RenderOptions::parse(arguments).map_err(Error::Format)?;
+	let document = bundle::read_bundle(&fs::read(&options.input)?)?;
+	for warning in document.warnings.iter() {
+		eprintln!("Warning: {}", warning);
+	}
+	let mut pages = document.pages;
+	if let Some(page) = options.page {
+		if page == 0 || page > pages.len() {
+			return Err(Error::Format(format!(
+				"Page {} does not exist, the notebook has {} pages.",
+				page,
+				pages.len()
+			)));
+		}
+		pages = vec![pages.swap_remove(page - 1)];
+	}
+	match (options.format, options.page) {
+		(OutputFormat::Pdf, _) => render::export_pdf(&options.output, &pages, options.size),
+		(OutputFormat::Png, Some(_)) => {
+			render::export_png(&options.output, &pages[0], options.size, options.scale)
+		}
+		(OutputFormat::Png, None) => {
+			fs::create_dir_all(&options.output)?;
+			render::export_pngs(
+				&options.output,
+				"page-{n}.png",
+				&pages,
+				options.size,
+				options.scale,
+			)
+		}
+		(OutputFormat::Svg, Some(_)) => svg::export_svg(&options.output, &pages[0], options.size),
+		(OutputFormat::Svg, None) => {
+			fs::create_dir_all(&options.output)?;
+			svg::export_svgs(&options.output.join("page.svg"), &pages, options.size)
+		}
+	}
+}
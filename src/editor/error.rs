@@ -0,0 +1,103 @@
+use gtk::prelude::*;
+use gtk::{ButtonsType, DialogFlags, MessageDialog, MessageType, Window};
+use std::fmt;
+use std::io;
+
+/// Errors that can occur while reading, writing, importing and exporting files.
+#[derive(Debug)]
+pub enum Error {
+	/// A document could not be read or written.
+	Document(hnote::error::Error),
+	/// A file could not be read or written.
+	Io(io::Error),
+	/// The preferences could not be serialized.
+	Json(serde_json::Error),
+	/// A cairo surface could not be created.
+	Cairo(cairo::Error),
+	/// An image could not be decoded or encoded.
+	Image(cairo::IoError),
+	/// An imported image could not be decoded by gdk-pixbuf.
+	Pixbuf(glib::Error),
+	/// An input or a file content is invalid.
+	Format(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Document(error) => write!(f, "{}", error),
+			Error::Io(error) => write!(f, "{}", error),
+			Error::Json(error) => write!(f, "Invalid preferences: {}", error),
+			Error::Cairo(error) => write!(f, "Could not create surface: {}", error),
+			Error::Image(error) => write!(f, "Invalid image: {}", error),
+			Error::Pixbuf(error) => write!(f, "Invalid image: {}", error),
+			Error::Format(message) => write!(f, "{}", message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Document(error) => Some(error),
+			Error::Io(error) => Some(error),
+			Error::Json(error) => Some(error),
+			Error::Cairo(error) => Some(error),
+			Error::Image(error) => Some(error),
+			Error::Pixbuf(error) => Some(error),
+			Error::Format(_) => None,
+		}
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Error::Io(error)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(error: serde_json::Error) -> Self {
+		Error::Json(error)
+	}
+}
+
+impl From<hnote::error::Error> for Error {
+	fn from(error: hnote::error::Error) -> Self {
+		Error::Document(error)
+	}
+}
+
+impl From<cairo::Error> for Error {
+	fn from(error: cairo::Error) -> Self {
+		Error::Cairo(error)
+	}
+}
+
+impl From<cairo::IoError> for Error {
+	fn from(error: cairo::IoError) -> Self {
+		Error::Image(error)
+	}
+}
+
+impl From<glib::Error> for Error {
+	fn from(error: glib::Error) -> Self {
+		Error::Pixbuf(error)
+	}
+}
+
+/// Shows a `gtk::MessageDialog` that tells the user what failed with `message` and why with `error`.
+pub fn show_error_dialog(parent: Option<&Window>, message: &str, error: &Error) {
+	let dialog = MessageDialog::new(
+		parent,
+		DialogFlags::DESTROY_WITH_PARENT | DialogFlags::MODAL,
+		MessageType::Error,
+		ButtonsType::Close,
+		message,
+	);
+	dialog.set_property_secondary_text(Some(&error.to_string()));
+	dialog.connect_response(|dialog, _| dialog.close());
+	dialog.show_all();
+}
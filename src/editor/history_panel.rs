@@ -0,0 +1,207 @@
+use crate::editor::render::draw_page;
+use crate::editor::widgets::{BufferedImage, ImageBuffer};
+use cairo::{Context, Format, ImageSurface};
+use glib::DateTime;
+use gtk::prelude::*;
+use gtk::*;
+use hnote::history::{EntryId, History, Operation};
+use hnote::model::Page;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+/// Width of the page thumbnails in pixels.
+const THUMBNAIL_WIDTH: f64 = 96.0;
+
+/// Left margin per branch level of the undo tree in pixels.
+const BRANCH_INDENT: i32 = 16;
+
+/// Window that lists all entries of a `History` as undo tree.
+///
+/// Each entry shows its time, the kind of its operation and a thumbnail of the page it changed.
+/// Entries that are not applied in the current state are dimmed.
+#[derive(Clone, Debug)]
+pub struct HistoryPanel {
+	window: Window,
+	list: ListBox,
+	/// Entry of each row, `None` is the oldest stored state.
+	rows: Rc<Mutex<Vec<Option<EntryId>>>>,
+	thumbnails: Rc<Mutex<ThumbnailCache>>,
+}
+
+/// Thumbnails of the entries that are listed, together with the index of the page that each entry changed.
+///
+/// The state after an entry never changes while it is stored, so its thumbnail is only drawn once.
+/// All thumbnails are dropped when the canvas size changes or the history is replaced.
+#[derive(Debug, Default)]
+struct ThumbnailCache {
+	canvas_size: (f64, f64),
+	entries: HashMap<EntryId, (usize, Option<ImageSurface>)>,
+}
+
+impl HistoryPanel {
+	pub fn new(parent: &ApplicationWindow) -> Self {
+		let window = Window::new(WindowType::Toplevel);
+		window.set_title("History");
+		window.set_transient_for(Some(parent));
+		window.set_default_size(300, 600);
+		window.connect_delete_event(|window, _| window.hide_on_delete());
+		let list = ListBox::new();
+		list.set_selection_mode(SelectionMode::Single);
+		let scrolled_window = ScrolledWindow::new(None::<&Adjustment>, None::<&Adjustment>);
+		scrolled_window.add(&list);
+		window.add(&scrolled_window);
+		Self {
+			window,
+			list,
+			rows: Rc::new(Mutex::new(Vec::new())),
+			thumbnails: Rc::new(Mutex::new(ThumbnailCache::default())),
+		}
+	}
+
+	/// Calls `jump` with the entry of a row when the row is activated.
+	pub fn connect_jump<F: Fn(Option<EntryId>) + 'static>(&self, jump: F) {
+		let rows = Rc::clone(&self.rows);
+		self.list.connect_row_activated(move |_, row| {
+			let entry = rows.lock().unwrap().get(row.get_index() as usize).cloned();
+			if let Some(entry) = entry {
+				jump(entry);
+			}
+		});
+	}
+
+	/// Shows the window, `refresh()` has to be called afterwards to fill it.
+	pub fn show(&self) {
+		self.window.show_all();
+		self.window.present();
+	}
+
+	/// Drops all cached thumbnails, has to be called when the history is replaced, as its entry ids are reused.
+	pub fn invalidate(&self) {
+		self.thumbnails.lock().unwrap().entries.clear();
+	}
+
+	/// Lists the entries of `history`, `pages` is the current state of the pages.
+	///
+	/// Nothing is done while the window is hidden, as the thumbnails are expensive to draw.
+	/// Only the thumbnails of entries that were added since the last call are drawn,
+	/// those of entries that were dropped from `history` are removed from the cache.
+	pub fn refresh(&self, history: &History, pages: &[Page], canvas_size: (f64, f64)) {
+		if !self.window.get_visible() {
+			return;
+		}
+		for child in self.list.get_children() {
+			self.list.remove(&child);
+		}
+		let mut thumbnails = self.thumbnails.lock().unwrap();
+		if thumbnails.canvas_size != canvas_size {
+			thumbnails.canvas_size = canvas_size;
+			thumbnails.entries.clear();
+		}
+		let tree = history.tree();
+		let listed: HashSet<EntryId> = tree.iter().map(|(id, _)| *id).collect();
+		thumbnails.entries.retain(|id, _| listed.contains(id));
+		if listed.len() > thumbnails.entries.len() {
+			let mut image_buffer = ImageBuffer::new();
+			let entries = &mut thumbnails.entries;
+			history.visit_states(pages, |id, pages, page_index| {
+				entries.entry(id).or_insert_with(|| {
+					let thumbnail = thumbnail(&pages[page_index], canvas_size, &mut image_buffer);
+					(page_index, thumbnail)
+				});
+			});
+		}
+
+		let applied: HashSet<EntryId> = history.applied().into_iter().collect();
+		let mut rows = vec![None];
+		self.list.add(&row_widget("Oldest stored state", 0, None));
+		for (id, level) in tree {
+			let entry = history.entry(id).unwrap();
+			let (page_index, thumbnail) = &thumbnails.entries[&id];
+			let time = DateTime::from_unix_local(entry.timestamp as i64)
+				.format("%x %X")
+				.filter(|_| entry.timestamp > 0)
+				.map_or_else(|| "Unknown time".to_string(), |time| time.to_string());
+			let text = format!(
+				"{}\n{}, page {}",
+				time,
+				operation_name(&entry.operation),
+				page_index + 1
+			);
+			let row = row_widget(&text, level, thumbnail.as_ref());
+			if !applied.contains(&id) {
+				row.set_opacity(0.5);
+			}
+			self.list.add(&row);
+			rows.push(Some(id));
+		}
+		let current = rows
+			.iter()
+			.position(|row| *row == history.current())
+			.unwrap_or(0);
+		*self.rows.lock().unwrap() = rows;
+		self.list.show_all();
+		self.list
+			.select_row(self.list.get_row_at_index(current as i32).as_ref());
+	}
+}
+
+/// Short description of the kind of `operation`.
+fn operation_name(operation: &Operation) -> &'static str {
+	match operation {
+		Operation::AddStroke { .. } => "Stroke",
+		Operation::ReplaceLines { .. } => "Edit lines",
+		Operation::MoveElements { .. } => "Move",
+		Operation::AddPage { .. } => "Add page",
+		Operation::RemovePage { .. } => "Delete page",
+		Operation::MovePage { .. } => "Move page",
+		Operation::ReplacePage { .. } => "Edit page",
+		Operation::AddImage { .. } => "Import image",
+		Operation::AddLayer { .. } => "Add layer",
+		Operation::RemoveLayer { .. } => "Delete layer",
+		Operation::MoveLayer { .. } => "Move layer",
+		Operation::ChangeLayer { .. } => "Change layer",
+		Operation::Group(_) => "Edit",
+	}
+}
+
+/// Composes the content of a history row with `text` and `thumbnail`, indented by the branch `level`.
+fn row_widget(text: &str, level: usize, thumbnail: Option<&ImageSurface>) -> Box {
+	let row = Box::new(Orientation::Horizontal, 6);
+	row.set_margin_start(level as i32 * BRANCH_INDENT);
+	if let Some(thumbnail) = thumbnail {
+		row.pack_start(&Image::from_surface(Some(thumbnail)), false, false, 0);
+	}
+	let label = Label::new(Some(text));
+	label.set_xalign(0.0);
+	row.pack_start(&label, true, true, 0);
+	row
+}
+
+/// Draws `page` scaled down to `THUMBNAIL_WIDTH`.
+///
+/// Images of `page` are decoded into `image_buffer` once, images that can not be decoded are skipped.
+fn thumbnail(
+	page: &Page,
+	canvas_size: (f64, f64),
+	image_buffer: &mut ImageBuffer,
+) -> Option<ImageSurface> {
+	for image in page.images() {
+		if !image_buffer.contains_key(&image.hash) {
+			if let Ok(buffered_image) = BufferedImage::from_image(image) {
+				image_buffer.insert(image.hash.clone(), buffered_image);
+			}
+		}
+	}
+	let scale = THUMBNAIL_WIDTH / canvas_size.0;
+	let surface = ImageSurface::create(
+		Format::ARgb32,
+		THUMBNAIL_WIDTH as i32,
+		(canvas_size.1 * scale).ceil() as i32,
+	)
+	.ok()?;
+	let cr = Context::new(&surface);
+	cr.scale(scale, scale);
+	draw_page(&cr, page, image_buffer);
+	Some(surface)
+}
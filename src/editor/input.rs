@@ -0,0 +1,94 @@
+use gdk::InputSource;
+use serde::{Deserialize, Serialize};
+
+/// Kinds of input devices that are configured separately.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputKind {
+	Pen,
+	Touch,
+	Mouse,
+	Touchpad,
+}
+
+impl InputKind {
+	/// Kind of a device with the GDK input `source`.
+	///
+	/// Both ends of a stylus count as pen, trackpoints, tablet pucks and unknown devices count as mouse.
+	pub fn from_source(source: Option<InputSource>) -> Self {
+		match source {
+			Some(InputSource::Pen) | Some(InputSource::Eraser) => InputKind::Pen,
+			Some(InputSource::Touchscreen) => InputKind::Touch,
+			Some(InputSource::Touchpad) => InputKind::Touchpad,
+			_ => InputKind::Mouse,
+		}
+	}
+}
+
+/// What the events of an input device do on the canvas.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum InputAction {
+	/// Events are passed to the current `DrawTool`.
+	Draw,
+	/// Dragging moves the visible part of the canvas.
+	Pan,
+	/// Events are dropped.
+	Ignore,
+}
+
+impl InputAction {
+	pub const ALL: [(InputAction, &'static str); 3] = [
+		(InputAction::Draw, "Draw"),
+		(InputAction::Pan, "Pan"),
+		(InputAction::Ignore, "Ignore"),
+	];
+}
+
+/// Serializable policy that decides for every kind of input device what its events do.
+///
+/// With `ignores_touch_near_pen` touches are dropped while a stylus is close to the screen, so a resting palm does not draw.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct InputPolicy {
+	pub pen: InputAction,
+	pub touch: InputAction,
+	pub mouse: InputAction,
+	pub touchpad: InputAction,
+	pub ignores_touch_near_pen: bool,
+}
+
+impl Default for InputPolicy {
+	fn default() -> Self {
+		Self {
+			pen: InputAction::Draw,
+			touch: InputAction::Pan,
+			mouse: InputAction::Draw,
+			touchpad: InputAction::Draw,
+			ignores_touch_near_pen: true,
+		}
+	}
+}
+
+impl InputPolicy {
+	/// Action for the events of a device of `kind`, `pen_in_proximity` tells whether a stylus is close to the screen.
+	pub fn action(&self, kind: InputKind, pen_in_proximity: bool) -> InputAction {
+		match kind {
+			InputKind::Pen => self.pen,
+			InputKind::Touch if pen_in_proximity && self.ignores_touch_near_pen => {
+				InputAction::Ignore
+			}
+			InputKind::Touch => self.touch,
+			InputKind::Mouse => self.mouse,
+			InputKind::Touchpad => self.touchpad,
+		}
+	}
+
+	/// The configured action of `kind`, without palm rejection.
+	pub fn action_mut(&mut self, kind: InputKind) -> &mut InputAction {
+		match kind {
+			InputKind::Pen => &mut self.pen,
+			InputKind::Touch => &mut self.touch,
+			InputKind::Mouse => &mut self.mouse,
+			InputKind::Touchpad => &mut self.touchpad,
+		}
+	}
+}
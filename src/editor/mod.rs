@@ -0,0 +1,12 @@
+pub mod application;
+pub mod cli;
+pub mod error;
+pub mod history_panel;
+pub mod input;
+pub mod page_cache;
+pub mod preferences;
+pub mod recovery;
+pub mod render;
+pub mod svg;
+pub mod viewport;
+pub mod widgets;
@@ -0,0 +1,227 @@
+use crate::editor::render;
+use crate::editor::viewport::Viewport;
+use crate::editor::widgets::ImageBuffer;
+use cairo::{Content, Context, Surface};
+use gtk::prelude::*;
+use gtk::DrawingArea;
+use hnote::model::{LineJoin, Page, Stroke};
+use hnote::outline::MITER_LIMIT;
+
+/// Number of points at the end of a stroke whose drawing changes when a point is appended.
+///
+/// The outline at a point depends on both neighbours, so a new point changes the outline around the previous last point.
+const CHANGING_POINTS: usize = 3;
+
+/// Color of the canvas around the page.
+const SURROUNDING_RGB: [f64; 3] = [0.8, 0.8, 0.8];
+
+/// Offscreen rendering of the finished content of the shown page, as the canvas widget shows it.
+///
+/// The content is split at the active layer: `below` holds the background, the layers beneath and the active layer
+/// without the stroke that is being drawn, `above` holds the layers on top of the active layer.
+/// On every redraw the surfaces are painted and only the live stroke is drawn in between.
+/// The surfaces are rendered again after `invalidate()` and when the page, its active layer, the canvas size or the `Viewport` change.
+#[derive(Debug, Default)]
+pub struct PageCache {
+	surfaces: Option<CachedSurfaces>,
+	/// Page, layer and line index of the stroke that is being drawn.
+	live_stroke: Option<(usize, usize, usize)>,
+}
+
+#[derive(Debug)]
+struct CachedSurfaces {
+	key: CacheKey,
+	below: Surface,
+	above: Option<Surface>,
+}
+
+/// State the cached surfaces were rendered for, besides the page content.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CacheKey {
+	page: usize,
+	active_layer: usize,
+	canvas_size: (f64, f64),
+	viewport: Viewport,
+}
+
+impl PageCache {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Drops the surfaces after the content of the shown page changed.
+	pub fn invalidate(&mut self) {
+		self.surfaces = None;
+	}
+
+	/// Keeps the line at `line` of the layer at `layer` on the page at `page` out of the surfaces until `finish_stroke()`.
+	///
+	/// The line has to be the newest one, so the surfaces rendered before it was added stay valid.
+	pub fn start_stroke(&mut self, page: usize, layer: usize, line: usize) {
+		self.live_stroke = Some((page, layer, line));
+	}
+
+	/// Puts the live stroke into the surfaces with the rest of the page.
+	pub fn finish_stroke(&mut self) {
+		self.live_stroke = None;
+		self.invalidate();
+	}
+
+	/// Composes the page at `page_index`, which has `canvas_size`, onto `cr` of the canvas widget through `viewport`.
+	///
+	/// The surfaces are rendered first if they are missing or were rendered for another state.
+	/// If they can not be created, the page is drawn directly.
+	pub fn draw(
+		&mut self,
+		cr: &Context,
+		page_index: usize,
+		page: &Page,
+		image_buffer: &ImageBuffer,
+		canvas_size: (f64, f64),
+		viewport: Viewport,
+	) {
+		let key = CacheKey {
+			page: page_index,
+			active_layer: page.active_layer_index(),
+			canvas_size,
+			viewport,
+		};
+		if self.surfaces.as_ref().map(|surfaces| surfaces.key) != Some(key) {
+			self.surfaces = self.render(cr, key, page, image_buffer).ok();
+		}
+		let surfaces = match &self.surfaces {
+			Some(surfaces) => surfaces,
+			None => {
+				paint_surrounding(cr);
+				cr.save();
+				clip_to_page(cr, key);
+				render::draw_page(cr, page, image_buffer);
+				cr.restore();
+				return;
+			}
+		};
+		cr.set_source_surface(&surfaces.below, 0.0, 0.0);
+		cr.paint();
+		if let Some(line) = self.live_line(page_index, page) {
+			let opacity = page.layers[key.active_layer].properties.opacity;
+			cr.save();
+			clip_to_page(cr, key);
+			render::with_opacity(cr, opacity, || render::draw_line(cr, line));
+			cr.restore();
+		}
+		if let Some(above) = &surfaces.above {
+			cr.set_source_surface(above, 0.0, 0.0);
+			cr.paint();
+		}
+	}
+
+	/// Page area (`[left, top, right, bottom]`) that the end of the live stroke on the page at `page_index` covers.
+	///
+	/// Comparing it before and after a point was added gives the area that has to be redrawn.
+	pub fn live_stroke_damage(&self, page_index: usize, page: &Page) -> Option<[f64; 4]> {
+		let line = self.live_line(page_index, page)?;
+		let points = &line.points[line.points.len().saturating_sub(CHANGING_POINTS)..];
+		let line_width = points
+			.iter()
+			.map(|point| point.line_width)
+			.fold(0.0, f64::max);
+		let padding = match line.style.join {
+			LineJoin::Miter => line_width * MITER_LIMIT / 2.0,
+			_ => line_width,
+		};
+		points
+			.iter()
+			.map(|point| {
+				[
+					point.position.0 - padding,
+					point.position.1 - padding,
+					point.position.0 + padding,
+					point.position.1 + padding,
+				]
+			})
+			.reduce(union)
+	}
+
+	/// The live stroke, if it is on the page at `page_index` and its layer is visible.
+	fn live_line<'a>(&self, page_index: usize, page: &'a Page) -> Option<&'a Stroke> {
+		let (live_page, layer, line) = self.live_stroke?;
+		let layer = page.layers.get(layer).filter(|_| live_page == page_index)?;
+		if layer.properties.visible {
+			layer.lines().get(line)
+		} else {
+			None
+		}
+	}
+
+	/// Renders `page` into new surfaces of the widget size that are similar to the target of `cr`.
+	fn render(
+		&self,
+		cr: &Context,
+		key: CacheKey,
+		page: &Page,
+		image_buffer: &ImageBuffer,
+	) -> Result<CachedSurfaces, cairo::Error> {
+		let (below_layers, above_layers) = page.layers.split_at(key.active_layer + 1);
+		let excluded_line = self
+			.live_stroke
+			.filter(|(page, layer, _)| *page == key.page && *layer == key.active_layer)
+			.map(|(_, layer, line)| (layer, line));
+		let size = key.viewport.size;
+		let below = cr
+			.get_target()
+			.create_similar(Content::ColorAlpha, size.0, size.1)?;
+		let below_cr = Context::new(&below);
+		paint_surrounding(&below_cr);
+		clip_to_page(&below_cr, key);
+		below_cr.set_source_rgb(1.0, 1.0, 1.0);
+		below_cr.paint();
+		render::draw_layers(&below_cr, below_layers, image_buffer, excluded_line);
+		let above = if above_layers.iter().any(|layer| layer.properties.visible) {
+			let above = cr
+				.get_target()
+				.create_similar(Content::ColorAlpha, size.0, size.1)?;
+			let above_cr = Context::new(&above);
+			clip_to_page(&above_cr, key);
+			render::draw_layers(&above_cr, above_layers, image_buffer, None);
+			Some(above)
+		} else {
+			None
+		};
+		Ok(CachedSurfaces { key, below, above })
+	}
+}
+
+/// Fills the target of `cr` with the color around the page.
+fn paint_surrounding(cr: &Context) {
+	cr.set_source_rgb(SURROUNDING_RGB[0], SURROUNDING_RGB[1], SURROUNDING_RGB[2]);
+	cr.paint();
+}
+
+/// Transforms `cr` to page coordinates with the viewport of `key` and clips it to the page.
+fn clip_to_page(cr: &Context, key: CacheKey) {
+	key.viewport.apply(cr);
+	cr.rectangle(0.0, 0.0, key.canvas_size.0, key.canvas_size.1);
+	cr.clip();
+}
+
+/// Smallest rectangle (`[left, top, right, bottom]`) that contains both rectangles.
+pub fn union(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+	[
+		a[0].min(b[0]),
+		a[1].min(b[1]),
+		a[2].max(b[2]),
+		a[3].max(b[3]),
+	]
+}
+
+/// Schedules a redraw of the pixels of `area` that the widget area `rectangle` (`[left, top, right, bottom]`) touches.
+pub fn queue_draw_rectangle(area: &DrawingArea, rectangle: [f64; 4]) {
+	let left = rectangle[0].floor();
+	let top = rectangle[1].floor();
+	area.queue_draw_area(
+		left as i32,
+		top as i32,
+		(rectangle[2] - left).ceil() as i32 + 1,
+		(rectangle[3] - top).ceil() as i32 + 1,
+	);
+}
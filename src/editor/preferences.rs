@@ -0,0 +1,100 @@
+use crate::editor::error::Result;
+use crate::editor::input::InputPolicy;
+use hnote::history::History;
+use hnote::pressure::PressureCurve;
+use hnote::tools::CurrentDrawTool;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Serializable user preferences.
+///
+/// Stored as JSON in the XDG config directory, missing values fall back to their defaults.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Preferences {
+	/// Number of `.hnote.bak.N` files that are kept when saving.
+	pub backup_count: usize,
+	/// Number of operations that can be undone.
+	pub history_depth: usize,
+	/// Memory in MiB that the undo history may occupy.
+	pub history_memory: usize,
+	/// Number of operations that are stored in a notebook to undo them in the next session, 0 stores none.
+	pub stored_history_depth: usize,
+	/// Mapping of stylus pressure to the width and opacity of drawn points.
+	pub pressure_curve: PressureCurve,
+	/// Tool that the eraser end of a stylus uses, `Eraser` or `LineEraser`.
+	pub stylus_eraser: CurrentDrawTool,
+	/// What the events of pens, touchscreens, mice and touchpads do on the canvas.
+	pub input_policy: InputPolicy,
+}
+
+impl Default for Preferences {
+	fn default() -> Self {
+		Self {
+			backup_count: 3,
+			history_depth: 1000,
+			history_memory: 64,
+			stored_history_depth: 100,
+			pressure_curve: PressureCurve::default(),
+			stylus_eraser: CurrentDrawTool::Eraser,
+			input_policy: InputPolicy::default(),
+		}
+	}
+}
+
+impl Preferences {
+	/// Loads the stored preferences.
+	///
+	/// Falls back to the default preferences if none are stored or they can not be read.
+	/// At least one operation can always be undone.
+	pub fn load() -> Self {
+		let mut preferences: Self = fs::read_to_string(Self::path())
+			.ok()
+			.and_then(|serialized| serde_json::from_str(&serialized).ok())
+			.unwrap_or_default();
+		preferences.history_depth = preferences.history_depth.max(1);
+		preferences
+	}
+
+	/// Stores `self` for the next start of the editor.
+	pub fn save(&self) -> Result<()> {
+		let path = Self::path();
+		if let Some(directory) = path.parent() {
+			fs::create_dir_all(directory)?;
+		}
+		fs::write(path, serde_json::to_string_pretty(self)?)?;
+		Ok(())
+	}
+
+	/// Creates an empty `History` with the limits of `self`.
+	pub fn history(&self) -> History {
+		History::new(self.history_depth, self.history_memory_bytes())
+	}
+
+	/// `self.history_memory` in bytes.
+	pub fn history_memory_bytes(&self) -> usize {
+		self.history_memory.saturating_mul(1024 * 1024)
+	}
+
+	fn path() -> PathBuf {
+		xdg_directory("XDG_CONFIG_HOME", &[".config"]).join("preferences.json")
+	}
+}
+
+/// Resolves the editor's directory in the XDG base directory `variable`.
+///
+/// If `variable` is not set, the base directory is `$HOME` joined with `fallback`.
+pub fn xdg_directory(variable: &str, fallback: &[&str]) -> PathBuf {
+	let base = match env::var_os(variable) {
+		Some(base) if !base.is_empty() => PathBuf::from(base),
+		_ => fallback.iter().fold(
+			env::var_os("HOME")
+				.map(PathBuf::from)
+				.unwrap_or_else(env::temp_dir),
+			|base, component| base.join(component),
+		),
+	};
+	base.join("handwritten-notetaking-editor")
+}
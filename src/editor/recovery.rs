@@ -0,0 +1,124 @@
+use crate::editor::error::Result;
+use crate::editor::preferences::xdg_directory;
+use fs2::FileExt;
+use hnote::bundle;
+use hnote::document::Document;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Seconds between two autosaves of the recovery file.
+pub const AUTOSAVE_INTERVAL: u32 = 30;
+
+/// Number of the next window of this process that starts a recovery session.
+static NEXT_WINDOW: AtomicUsize = AtomicUsize::new(0);
+
+/// Crash recovery journal of one window in the XDG state directory.
+///
+/// Every window writes its own recovery file, named after the process id and the window number, and removes it when it is closed.
+/// While the window is open it holds a lock on the lock file next to its recovery file,
+/// a recovery file whose lock file is not locked is left from an unclean shutdown.
+#[derive(Clone, Debug)]
+pub struct Recovery {
+	directory: PathBuf,
+	/// Process id and window number, unique among all running editors.
+	session: String,
+	/// Lock file of the session, it is locked while the session is running.
+	lock: Rc<Mutex<Option<File>>>,
+}
+
+impl Recovery {
+	pub fn new() -> Self {
+		Self {
+			directory: xdg_directory("XDG_STATE_HOME", &[".local", "state"]),
+			session: format!(
+				"{}-{}",
+				std::process::id(),
+				NEXT_WINDOW.fetch_add(1, Ordering::Relaxed)
+			),
+			lock: Rc::new(Mutex::new(None)),
+		}
+	}
+
+	fn recovery_path(&self) -> PathBuf {
+		self.directory
+			.join(format!("recovery-{}.hnote", self.session))
+	}
+
+	/// Locks the lock file of `self` and takes over the recovery file of a window that is no longer open.
+	///
+	/// The file is renamed to the recovery file of `self`, so every recovered notebook is only offered to one window.
+	/// Returns whether a recovery file of an unclean shutdown was taken over.
+	pub fn start_session(&self) -> Result<bool> {
+		fs::create_dir_all(&self.directory)?;
+		let lock = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(lock_path(&self.recovery_path()))?;
+		lock.try_lock_exclusive()?;
+		*self.lock.lock().unwrap() = Some(lock);
+		for entry in fs::read_dir(&self.directory)? {
+			let path = entry?.path();
+			if is_orphaned(&path) && fs::rename(&path, self.recovery_path()).is_ok() {
+				let _ = fs::remove_file(lock_path(&path));
+				return Ok(true);
+			}
+		}
+		Ok(false)
+	}
+
+	/// Removes the recovery file and unlocks the lock file, the window was closed cleanly.
+	pub fn end_session(&self) {
+		self.discard();
+		if let Some(lock) = self.lock.lock().unwrap().take() {
+			let _ = fs::remove_file(lock_path(&self.recovery_path()));
+			drop(lock);
+		}
+	}
+
+	/// Writes `document` to the recovery file.
+	///
+	/// The previous recovery file is only replaced once `document` has been written completely.
+	pub fn write(&self, document: &Document) -> Result<()> {
+		Ok(bundle::save_bundle(&self.recovery_path(), document, 0)?)
+	}
+
+	/// Reads the document from the recovery file.
+	pub fn read(&self) -> Result<Document> {
+		Ok(bundle::read_bundle(&fs::read(self.recovery_path())?)?)
+	}
+
+	/// Removes the recovery file.
+	pub fn discard(&self) {
+		let _ = fs::remove_file(self.recovery_path());
+	}
+}
+
+impl Default for Recovery {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Lock file of the recovery file at `path`.
+fn lock_path(path: &Path) -> PathBuf {
+	path.with_extension("lock")
+}
+
+/// Checks whether `path` is a recovery file of a window that is no longer open.
+///
+/// The lock file of an open window can not be locked, a missing lock file has no window either.
+fn is_orphaned(path: &Path) -> bool {
+	let is_recovery_file = match path.file_name().and_then(|name| name.to_str()) {
+		Some(name) => name.starts_with("recovery-") && name.ends_with(".hnote"),
+		None => false,
+	};
+	is_recovery_file
+		&& match OpenOptions::new().write(true).open(lock_path(path)) {
+			Ok(lock) => lock.try_lock_exclusive().is_ok(),
+			Err(error) => error.kind() == std::io::ErrorKind::NotFound,
+		}
+}
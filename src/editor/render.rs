@@ -0,0 +1,156 @@
+use crate::editor::error::{Error, Result};
+use crate::editor::widgets::{page_image_buffer, ImageBuffer};
+use cairo::{Context, FillRule, Format, ImageSurface, PdfSurface};
+use hnote::model::{Layer, Page, Stroke};
+use hnote::outline::stroke_outlines;
+use std::fs::File;
+use std::path::Path;
+
+/// Composes `page` onto `cr`.
+///
+/// The page is painted white, then the visible layers are drawn from bottom to top with their opacity.
+/// `image_buffer` holds the decoded images of `page`, images that are not in it are skipped.
+pub fn draw_page(cr: &Context, page: &Page, image_buffer: &ImageBuffer) {
+	cr.set_source_rgb(1.0, 1.0, 1.0);
+	cr.paint();
+	draw_layers(cr, &page.layers, image_buffer, None);
+}
+
+/// Composes the visible `layers` onto `cr` from bottom to top with their opacity.
+///
+/// `excluded_line` names a line that is left out by the index of its layer in `layers` and its index in the lines of that layer.
+pub fn draw_layers(
+	cr: &Context,
+	layers: &[Layer],
+	image_buffer: &ImageBuffer,
+	excluded_line: Option<(usize, usize)>,
+) {
+	for (i, layer) in layers.iter().enumerate() {
+		if !layer.properties.visible {
+			continue;
+		}
+		let excluded_line = excluded_line
+			.filter(|(layer_index, _)| *layer_index == i)
+			.map(|(_, line_index)| line_index);
+		with_opacity(cr, layer.properties.opacity, || {
+			draw_layer(cr, layer, image_buffer, excluded_line)
+		});
+	}
+}
+
+/// Composes whatever `draw` draws onto `cr` as a whole with `opacity`.
+pub fn with_opacity(cr: &Context, opacity: f64, draw: impl FnOnce()) {
+	if opacity < 1.0 {
+		cr.push_group();
+		draw();
+		cr.pop_group_to_source();
+		cr.paint_with_alpha(opacity);
+	} else {
+		draw();
+	}
+}
+
+/// Composes the images of `layer` and its lines above them onto `cr`, except the line at `excluded_line`.
+fn draw_layer(
+	cr: &Context,
+	layer: &Layer,
+	image_buffer: &ImageBuffer,
+	excluded_line: Option<usize>,
+) {
+	for image in layer.images.iter() {
+		let buffered_image = match image_buffer.get(&image.hash) {
+			Some(buffered_image) => buffered_image,
+			None => continue,
+		};
+		cr.set_source_surface(
+			&buffered_image.image_surface,
+			image.position.0,
+			image.position.1,
+		);
+		cr.paint();
+	}
+	for (i, line) in layer.lines().iter().enumerate() {
+		if excluded_line != Some(i) {
+			draw_line(cr, line);
+		}
+	}
+}
+
+/// Composes `line` onto `cr` as the filled outlines of `stroke_outlines()`.
+///
+/// The outlines follow the width and colour of every `Drawpoint` and the caps, joins and dash pattern of the `StrokeStyle`.
+pub fn draw_line(cr: &Context, line: &Stroke) {
+	cr.set_fill_rule(FillRule::Winding);
+	for outline in stroke_outlines(line) {
+		cr.set_source_rgba(
+			outline.rgba[0],
+			outline.rgba[1],
+			outline.rgba[2],
+			outline.rgba[3],
+		);
+		for point in outline.points.iter() {
+			cr.line_to(point.0, point.1);
+		}
+		cr.close_path();
+		cr.fill();
+	}
+}
+
+/// Renders all `pages` into the PDF file at `path`, one PDF page per notebook page.
+///
+/// Every PDF page has the canvas `size`, lines are kept as vectors.
+pub fn export_pdf(path: &Path, pages: &[Page], size: (f64, f64)) -> Result<()> {
+	let image_buffers = pages
+		.iter()
+		.map(page_image_buffer)
+		.collect::<Result<Vec<_>>>()?;
+	let surface = PdfSurface::new(size.0, size.1, path)?;
+	let cr = Context::new(&surface);
+	for (page, image_buffer) in pages.iter().zip(image_buffers.iter()) {
+		draw_page(&cr, page, image_buffer);
+		cr.show_page();
+	}
+	surface.finish();
+	Ok(())
+}
+
+/// Renders `page` into the PNG file at `path`.
+///
+/// The image resolution is the canvas `size` multiplied with `scale`.
+pub fn export_png(path: &Path, page: &Page, size: (f64, f64), scale: f64) -> Result<()> {
+	let image_buffer = page_image_buffer(page)?;
+	let surface = ImageSurface::create(
+		Format::ARgb32,
+		(size.0 * scale).ceil() as i32,
+		(size.1 * scale).ceil() as i32,
+	)?;
+	let cr = Context::new(&surface);
+	cr.scale(scale, scale);
+	draw_page(&cr, page, &image_buffer);
+	let mut png = File::create(path)?;
+	surface.write_to_png(&mut png)?;
+	Ok(())
+}
+
+/// Renders every page of `pages` into a PNG file in `directory`.
+///
+/// The file names are created from `pattern` by replacing `{n}` with the page number.
+/// The image resolution is the canvas `size` multiplied with `scale`.
+pub fn export_pngs(
+	directory: &Path,
+	pattern: &str,
+	pages: &[Page],
+	size: (f64, f64),
+	scale: f64,
+) -> Result<()> {
+	if !pattern.contains("{n}") {
+		return Err(Error::Format(
+			"The file name pattern has to contain {n}.".to_string(),
+		));
+	}
+	for (i, page) in pages.iter().enumerate() {
+		let file_name = pattern.replace("{n}", &(i + 1).to_string());
+		export_png(&directory.join(file_name), page, size, scale)?;
+	}
+	Ok(())
+}
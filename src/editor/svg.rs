@@ -0,0 +1,109 @@
+use crate::editor::error::Result;
+use crate::editor::widgets::{page_image_buffer, ImageBuffer};
+use hnote::model::*;
+use hnote::outline::{stroke_outlines, Outline};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Serializes `page` as an SVG document with the canvas `size`.
+///
+/// Every visible layer becomes a group with the opacity of the layer.
+/// `image_buffer` holds the decoded images of `page`, they are embedded as base64 encoded PNGs.
+/// Images that are not in `image_buffer` are skipped.
+/// Every line becomes a group of filled paths around the same outlines that are drawn on the canvas.
+pub fn page_to_svg(page: &Page, image_buffer: &ImageBuffer, size: (f64, f64)) -> Result<String> {
+	let mut svg = format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+		 width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+		size.0, size.1
+	);
+	svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+	for layer in page.layers.iter().filter(|layer| layer.properties.visible) {
+		svg.push_str(&layer_to_svg(layer, image_buffer)?);
+	}
+	svg.push_str("</svg>\n");
+	Ok(svg)
+}
+
+/// Converts the images of `layer` and its lines above them into a group with the opacity of `layer`.
+fn layer_to_svg(layer: &Layer, image_buffer: &ImageBuffer) -> Result<String> {
+	let mut svg = format!("<g opacity=\"{}\">\n", layer.properties.opacity);
+	for image in layer.images.iter() {
+		let buffered_image = match image_buffer.get(&image.hash) {
+			Some(buffered_image) => buffered_image,
+			None => continue,
+		};
+		let mut png = Vec::new();
+		buffered_image.image_surface.write_to_png(&mut png)?;
+		svg.push_str(&format!(
+			"<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" xlink:href=\"data:image/png;base64,{}\"/>\n",
+			image.position.0,
+			image.position.1,
+			buffered_image.image_surface.get_width(),
+			buffered_image.image_surface.get_height(),
+			base64::encode(&png)
+		));
+	}
+	for line in layer.lines().iter() {
+		svg.push_str(&line_to_svg(line));
+	}
+	svg.push_str("</g>\n");
+	Ok(svg)
+}
+
+/// Converts `stroke` into a group with a filled path for each of its outlines.
+fn line_to_svg(stroke: &Stroke) -> String {
+	let mut paths = "<g>\n".to_string();
+	for outline in stroke_outlines(stroke) {
+		paths.push_str(&path(&outline));
+	}
+	paths.push_str("</g>\n");
+	paths
+}
+
+/// Creates a closed path around the polygon of `outline`, filled with its colour by the nonzero rule.
+fn path(outline: &Outline) -> String {
+	let mut data = String::new();
+	for (i, point) in outline.points.iter().enumerate() {
+		let command = if i == 0 { "M" } else { " L" };
+		data.push_str(&format!("{} {} {}", command, point.0, point.1));
+	}
+	format!(
+		"<path d=\"{} Z\" fill=\"rgb({},{},{})\" fill-opacity=\"{}\" fill-rule=\"nonzero\"/>\n",
+		data,
+		(outline.rgba[0] * 255.0).round(),
+		(outline.rgba[1] * 255.0).round(),
+		(outline.rgba[2] * 255.0).round(),
+		outline.rgba[3]
+	)
+}
+
+/// Writes `page` as SVG file to `path`.
+pub fn export_svg(path: &Path, page: &Page, size: (f64, f64)) -> Result<()> {
+	let svg = page_to_svg(page, &page_image_buffer(page)?, size)?;
+	fs::write(path, svg)?;
+	Ok(())
+}
+
+/// Writes every page of `pages` as numbered SVG file next to `path`.
+///
+/// `notes.svg` becomes `notes-1.svg`, `notes-2.svg` and so on.
+pub fn export_svgs(path: &Path, pages: &[Page], size: (f64, f64)) -> Result<()> {
+	for (i, page) in pages.iter().enumerate() {
+		export_svg(&numbered_path(path, i + 1), page, size)?;
+	}
+	Ok(())
+}
+
+/// Inserts `number` between the file stem and the extension of `path`.
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+	let stem = path
+		.file_stem()
+		.map(|stem| stem.to_string_lossy().into_owned())
+		.unwrap_or_default();
+	let extension = path
+		.extension()
+		.map(|extension| extension.to_string_lossy().into_owned())
+		.unwrap_or_else(|| "svg".to_string());
+	path.with_file_name(format!("{}-{}.{}", stem, number, extension))
+}
@@ -0,0 +1,91 @@
+use cairo::Context;
+
+/// Smallest scale of the canvas.
+pub const MIN_SCALE: f64 = 0.1;
+
+/// Largest scale of the canvas.
+pub const MAX_SCALE: f64 = 10.0;
+
+/// Factor by which one step of zooming in scales the canvas.
+pub const ZOOM_STEP: f64 = 1.25;
+
+/// Distance in pixels that one step of a scroll wheel moves the canvas.
+pub const SCROLL_DISTANCE: f64 = 40.0;
+
+/// Transform from page coordinates, in which all strokes and images are stored, to the pixels of the canvas widget.
+///
+/// A page position `p` is shown at `p * scale + offset`. `size` is the size of the widget in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+	pub scale: f64,
+	pub offset: (f64, f64),
+	pub size: (i32, i32),
+}
+
+impl Default for Viewport {
+	fn default() -> Self {
+		Self {
+			scale: 1.0,
+			offset: (0.0, 0.0),
+			size: (0, 0),
+		}
+	}
+}
+
+impl Viewport {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Page position that is shown at the widget position `position`.
+	pub fn to_page(&self, position: (f64, f64)) -> (f64, f64) {
+		(
+			(position.0 - self.offset.0) / self.scale,
+			(position.1 - self.offset.1) / self.scale,
+		)
+	}
+
+	/// Widget position at which the page position `position` is shown.
+	pub fn to_widget(&self, position: (f64, f64)) -> (f64, f64) {
+		(
+			position.0 * self.scale + self.offset.0,
+			position.1 * self.scale + self.offset.1,
+		)
+	}
+
+	/// Widget area (`[left, top, right, bottom]`) that shows the page area `rectangle`.
+	pub fn rectangle_to_widget(&self, rectangle: [f64; 4]) -> [f64; 4] {
+		let (left, top) = self.to_widget((rectangle[0], rectangle[1]));
+		let (right, bottom) = self.to_widget((rectangle[2], rectangle[3]));
+		[left, top, right, bottom]
+	}
+
+	/// Center of the widget.
+	pub fn center(&self) -> (f64, f64) {
+		(self.size.0 as f64 / 2.0, self.size.1 as f64 / 2.0)
+	}
+
+	/// Transforms `cr`, which draws onto the widget, to draw in page coordinates.
+	pub fn apply(&self, cr: &Context) {
+		cr.translate(self.offset.0, self.offset.1);
+		cr.scale(self.scale, self.scale);
+	}
+
+	/// Changes the scale to `scale`, clamped to `MIN_SCALE` and `MAX_SCALE`.
+	///
+	/// The page position shown at the widget position `center` stays in place.
+	pub fn zoom_to(&mut self, scale: f64, center: (f64, f64)) {
+		let fixed_position = self.to_page(center);
+		self.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+		self.offset = (
+			center.0 - fixed_position.0 * self.scale,
+			center.1 - fixed_position.1 * self.scale,
+		);
+	}
+
+	/// Moves the shown page by `distance` widget pixels.
+	pub fn pan(&mut self, distance: (f64, f64)) {
+		self.offset.0 += distance.0;
+		self.offset.1 += distance.1;
+	}
+}
@@ -0,0 +1,150 @@
+use crate::editor::error::{show_error_dialog, Error, Result};
+use cairo::{Context, Format, ImageSurface};
+use gdk::prelude::GdkContextExt;
+use gdk_pixbuf::prelude::*;
+use gdk_pixbuf::PixbufLoader;
+use glib::clone;
+use gtk::prelude::*;
+use gtk::*;
+use hnote::model::{Image, Layer, Page};
+use hnote::tools::CurrentDrawTool;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+/// Decoded images of the displayed page, keyed by their content hash.
+pub type ImageBuffer = HashMap<String, BufferedImage>;
+
+/// Places a button with `label` in `pack` that sets `current_draw_tool` to `draw_tool` on click.
+///
+/// `area` is redrawn afterwards, since its overlays depend on the tool.
+pub fn tool_button(
+	label: &str,
+	draw_tool: CurrentDrawTool,
+	current_draw_tool: Rc<Mutex<CurrentDrawTool>>,
+	area: &DrawingArea,
+	pack: &Box,
+) -> Button {
+	let button = Button::with_label(label);
+	button.connect_clicked(clone!(@strong area => move |_| {
+		*current_draw_tool.lock().unwrap() = draw_tool.clone();
+		area.queue_draw();
+	}));
+	pack.pack_start(&button, false, false, 0);
+	button
+}
+
+/// Blueprint for an image instance of type `gtk::ImageSurface`.
+#[derive(Clone, Debug)]
+pub struct BufferedImage {
+	pub image_surface: ImageSurface,
+}
+
+impl BufferedImage {
+	pub fn new(image_surface: ImageSurface) -> Self {
+		Self { image_surface }
+	}
+
+	/// Decodes the embedded data of `image` into an `ImageSurface`.
+	///
+	/// Every format that gdk-pixbuf can load is supported, the EXIF orientation of camera images is applied.
+	pub fn from_image(image: &Image) -> Result<Self> {
+		let loader = PixbufLoader::new();
+		loader.write(&image.data)?;
+		loader.close()?;
+		let pixbuf = loader
+			.get_pixbuf()
+			.ok_or_else(|| Error::Format("The image could not be decoded.".to_string()))?;
+		let pixbuf = pixbuf.apply_embedded_orientation().unwrap_or(pixbuf);
+		let image_surface =
+			ImageSurface::create(Format::ARgb32, pixbuf.get_width(), pixbuf.get_height())?;
+		let cr = Context::new(&image_surface);
+		cr.set_source_pixbuf(&pixbuf, 0.0, 0.0);
+		cr.paint();
+		Ok(Self::new(image_surface))
+	}
+
+	/// Displayed width and height of the image.
+	pub fn size(&self) -> (f64, f64) {
+		(
+			self.image_surface.get_width() as f64,
+			self.image_surface.get_height() as f64,
+		)
+	}
+}
+
+/// Updates `image_buffer` to contain exactly the images of all layers of `page`.
+///
+/// Images that are already decoded are kept, all others are decoded.
+/// If an image can not be decoded, `image_buffer` is left unchanged.
+pub fn reload_image_buffer(page: &Page, image_buffer: &mut ImageBuffer) -> Result<()> {
+	let mut reloaded = ImageBuffer::new();
+	for image in page.images() {
+		if reloaded.contains_key(&image.hash) {
+			continue;
+		}
+		let buffered_image = match image_buffer.get(&image.hash) {
+			Some(buffered_image) => buffered_image.clone(),
+			None => BufferedImage::from_image(image)?,
+		};
+		reloaded.insert(image.hash.clone(), buffered_image);
+	}
+	*image_buffer = reloaded;
+	Ok(())
+}
+
+/// Decodes all images of `page` into a new `ImageBuffer`.
+pub fn page_image_buffer(page: &Page) -> Result<ImageBuffer> {
+	let mut image_buffer = ImageBuffer::new();
+	reload_image_buffer(page, &mut image_buffer)?;
+	Ok(image_buffer)
+}
+
+/// Collects the displayed sizes of all images of `layer` in the order expected by `DrawTool::manipulate`.
+///
+/// Images that are not decoded have no size.
+pub fn image_sizes(layer: &Layer, image_buffer: &ImageBuffer) -> Vec<(f64, f64)> {
+	layer
+		.images
+		.iter()
+		.map(|image| {
+			image_buffer
+				.get(&image.hash)
+				.map_or((0.0, 0.0), BufferedImage::size)
+		})
+		.collect()
+}
+
+/// Places a button in the application that shows a page on click.
+///
+/// Connects a `gtk::Button` in `pack` that sets `current_page` to the position of the button.
+/// When loading the page, `image_buffer` is updated based on the page images.
+pub fn connect_page_button(
+	pages: Rc<Mutex<Vec<Page>>>,
+	current_page: Rc<Mutex<usize>>,
+	image_buffer: Rc<Mutex<ImageBuffer>>,
+	area: DrawingArea,
+	pack: &Box,
+) -> Button {
+	let button = Button::with_label("Page");
+	pack.pack_start(&button, false, false, 0);
+	button.connect_clicked(
+		clone!(@strong pages, @strong current_page, @strong image_buffer, @strong pack, @strong button => move |_| {
+			let button_position = pack.get_child_position(&button) as usize;
+			*current_page.lock().unwrap() = button_position;
+			let reloaded = {
+				let pages = pages.lock().unwrap();
+				let mut image_buffer = image_buffer.lock().unwrap();
+				reload_image_buffer(&pages[button_position], &mut image_buffer)
+			};
+			if let Err(error) = reloaded {
+				let window = button.get_toplevel().and_then(|widget| widget.downcast::<Window>().ok());
+				show_error_dialog(window.as_ref(), "Could not load the images of the page.", &error);
+			}
+			area.queue_draw();
+		}),
+	);
+	let button_position = pack.get_child_position(&button);
+	pack.set_child_position(&button, button_position - 4);
+	button
+}
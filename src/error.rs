@@ -0,0 +1,58 @@
+use std::fmt;
+use std::io;
+use zip::result::ZipError;
+
+/// Errors that can occur while reading and writing documents.
+#[derive(Debug)]
+pub enum Error {
+	/// A file could not be read or written.
+	Io(io::Error),
+	/// A document could not be serialized or deserialized.
+	Json(serde_json::Error),
+	/// A bundle archive could not be read or written.
+	Zip(ZipError),
+	/// The content of a document is invalid.
+	Format(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Io(error) => write!(f, "{}", error),
+			Error::Json(error) => write!(f, "Invalid document: {}", error),
+			Error::Zip(error) => write!(f, "Invalid bundle: {}", error),
+			Error::Format(message) => write!(f, "{}", message),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io(error) => Some(error),
+			Error::Json(error) => Some(error),
+			Error::Zip(error) => Some(error),
+			Error::Format(_) => None,
+		}
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Self {
+		Error::Io(error)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(error: serde_json::Error) -> Self {
+		Error::Json(error)
+	}
+}
+
+impl From<ZipError> for Error {
+	fn from(error: ZipError) -> Self {
+		Error::Zip(error)
+	}
+}
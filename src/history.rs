@@ -0,0 +1,1029 @@
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+/// A reversible change of the pages of a notebook.
+///
+/// Only the changed data is stored, `apply()` and `revert()` turn the pages into the state after and before the change.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Operation {
+	/// A new stroke was drawn at `index` of the lines of `layer` of `page`.
+	AddStroke {
+		page: usize,
+		layer: usize,
+		index: usize,
+		stroke: Stroke,
+	},
+	/// The lines of `layer` of `page` from `index` on were replaced, e.g. by erasing points, erasing lines or clearing the page.
+	ReplaceLines {
+		page: usize,
+		layer: usize,
+		index: usize,
+		removed: Vec<Stroke>,
+		inserted: Vec<Stroke>,
+	},
+	/// The lines and images of `layer` of `page` with the ids in `lines` and `images` were translated by `vector`.
+	MoveElements {
+		page: usize,
+		layer: usize,
+		lines: Vec<ElementId>,
+		images: Vec<ElementId>,
+		vector: (f64, f64),
+	},
+	/// `page` was inserted at `index`.
+	AddPage { index: usize, page: Page },
+	/// `page` was removed from `index`.
+	RemovePage { index: usize, page: Page },
+	/// The page at `from` was moved to `to`.
+	MovePage { from: usize, to: usize },
+	/// The page at `index` was replaced as a whole, e.g. because its layers changed in several ways.
+	ReplacePage {
+		index: usize,
+		before: Page,
+		after: Page,
+	},
+	/// `image` was inserted at `index` of the images of `layer` of `page`.
+	AddImage {
+		page: usize,
+		layer: usize,
+		index: usize,
+		image: Image,
+	},
+	/// `layer` was inserted at `index` of the layers of `page`.
+	AddLayer {
+		page: usize,
+		index: usize,
+		layer: Layer,
+	},
+	/// `layer` was removed from `index` of the layers of `page`.
+	RemoveLayer {
+		page: usize,
+		index: usize,
+		layer: Layer,
+	},
+	/// The layer at `from` of `page` was moved to `to`.
+	MoveLayer { page: usize, from: usize, to: usize },
+	/// The properties of the layer at `index` of `page` were changed from `before` to `after`.
+	ChangeLayer {
+		page: usize,
+		index: usize,
+		before: LayerProperties,
+		after: LayerProperties,
+	},
+	/// Several operations that are undone and redone together.
+	Group(Vec<Operation>),
+}
+
+impl Operation {
+	/// Describes the replacement of the lines `removed` from `index` on by `inserted` in `layer` of `page`.
+	///
+	/// Lines that are equal at the start and the end of both are left out.
+	/// Returns `None` if nothing changed.
+	pub fn replace_lines(
+		page: usize,
+		layer: usize,
+		index: usize,
+		removed: &[Stroke],
+		inserted: &[Stroke],
+	) -> Option<Self> {
+		let prefix = removed
+			.iter()
+			.zip(inserted.iter())
+			.take_while(|(from, to)| from == to)
+			.count();
+		if prefix == removed.len() && prefix == inserted.len() {
+			return None;
+		}
+		let suffix = removed[prefix..]
+			.iter()
+			.rev()
+			.zip(inserted[prefix..].iter().rev())
+			.take_while(|(from, to)| from == to)
+			.count();
+		let removed = removed[prefix..removed.len() - suffix].to_vec();
+		let mut inserted = inserted[prefix..inserted.len() - suffix].to_vec();
+		if removed.is_empty() && inserted.len() == 1 {
+			return Some(Operation::AddStroke {
+				page,
+				layer,
+				index: index + prefix,
+				stroke: inserted.pop().unwrap(),
+			});
+		}
+		Some(Operation::ReplaceLines {
+			page,
+			layer,
+			index: index + prefix,
+			removed,
+			inserted,
+		})
+	}
+
+	/// Applies `self` to `pages`.
+	///
+	/// Returns the index of the page that shows the change.
+	pub fn apply(&self, pages: &mut Vec<Page>) -> usize {
+		match self {
+			Operation::AddStroke {
+				page,
+				layer,
+				index,
+				stroke,
+			} => {
+				pages[*page].layers[*layer].splice_lines(*index..*index, vec![stroke.clone()]);
+				*page
+			}
+			Operation::ReplaceLines {
+				page,
+				layer,
+				index,
+				removed,
+				inserted,
+			} => {
+				pages[*page].layers[*layer]
+					.splice_lines(*index..*index + removed.len(), inserted.clone());
+				*page
+			}
+			Operation::MoveElements {
+				page,
+				layer,
+				lines,
+				images,
+				vector,
+			} => {
+				pages[*page].layers[*layer].translate(lines, images, *vector);
+				*page
+			}
+			Operation::AddPage { index, page } => {
+				pages.insert(*index, page.clone());
+				*index
+			}
+			Operation::RemovePage { index, .. } => {
+				pages.remove(*index);
+				(*index).min(pages.len() - 1)
+			}
+			Operation::MovePage { from, to } => {
+				let page = pages.remove(*from);
+				pages.insert(*to, page);
+				*to
+			}
+			Operation::ReplacePage { index, after, .. } => {
+				pages[*index] = after.clone();
+				*index
+			}
+			Operation::AddImage {
+				page,
+				layer,
+				index,
+				image,
+			} => {
+				pages[*page].layers[*layer]
+					.images
+					.insert(*index, image.clone());
+				*page
+			}
+			Operation::AddLayer { page, index, layer } => {
+				pages[*page].layers.insert(*index, layer.clone());
+				pages[*page].active_layer = *index;
+				*page
+			}
+			Operation::RemoveLayer { page, index, .. } => {
+				remove_layer(&mut pages[*page], *index);
+				*page
+			}
+			Operation::MoveLayer { page, from, to } => {
+				move_layer(&mut pages[*page], *from, *to);
+				*page
+			}
+			Operation::ChangeLayer {
+				page, index, after, ..
+			} => {
+				pages[*page].layers[*index].properties = after.clone();
+				*page
+			}
+			Operation::Group(operations) => operations
+				.iter()
+				.fold(0, |_, operation| operation.apply(pages)),
+		}
+	}
+
+	/// Reverts `self` on `pages`, which have to be in the state after `apply()`.
+	///
+	/// Returns the index of the page that shows the change.
+	pub fn revert(&self, pages: &mut Vec<Page>) -> usize {
+		match self {
+			Operation::AddStroke {
+				page, layer, index, ..
+			} => {
+				pages[*page].layers[*layer].splice_lines(*index..*index + 1, Vec::new());
+				*page
+			}
+			Operation::ReplaceLines {
+				page,
+				layer,
+				index,
+				removed,
+				inserted,
+			} => {
+				pages[*page].layers[*layer]
+					.splice_lines(*index..*index + inserted.len(), removed.clone());
+				*page
+			}
+			Operation::MoveElements {
+				page,
+				layer,
+				lines,
+				images,
+				vector,
+			} => {
+				pages[*page].layers[*layer].translate(lines, images, (-vector.0, -vector.1));
+				*page
+			}
+			Operation::AddPage { index, .. } => {
+				pages.remove(*index);
+				(*index).min(pages.len() - 1)
+			}
+			Operation::RemovePage { index, page } => {
+				pages.insert(*index, page.clone());
+				*index
+			}
+			Operation::MovePage { from, to } => {
+				let page = pages.remove(*to);
+				pages.insert(*from, page);
+				*from
+			}
+			Operation::ReplacePage { index, before, .. } => {
+				pages[*index] = before.clone();
+				*index
+			}
+			Operation::AddImage {
+				page, layer, index, ..
+			} => {
+				pages[*page].layers[*layer].images.remove(*index);
+				*page
+			}
+			Operation::AddLayer { page, index, .. } => {
+				remove_layer(&mut pages[*page], *index);
+				*page
+			}
+			Operation::RemoveLayer { page, index, layer } => {
+				pages[*page].layers.insert(*index, layer.clone());
+				pages[*page].active_layer = *index;
+				*page
+			}
+			Operation::MoveLayer { page, from, to } => {
+				move_layer(&mut pages[*page], *to, *from);
+				*page
+			}
+			Operation::ChangeLayer {
+				page,
+				index,
+				before,
+				..
+			} => {
+				pages[*page].layers[*index].properties = before.clone();
+				*page
+			}
+			Operation::Group(operations) => operations
+				.iter()
+				.rev()
+				.fold(0, |_, operation| operation.revert(pages)),
+		}
+	}
+
+	/// Combines `self` with `next`, which was applied right after it, if both change the same properties of the same layer.
+	fn merged(&self, next: &Operation) -> Option<Self> {
+		match (self, next) {
+			(
+				Operation::ChangeLayer {
+					page,
+					index,
+					before,
+					after,
+				},
+				Operation::ChangeLayer {
+					page: next_page,
+					index: next_index,
+					before: next_before,
+					after: next_after,
+				},
+			) if page == next_page
+				&& index == next_index
+				&& changed_properties(before, after)
+					== changed_properties(next_before, next_after) =>
+			{
+				Some(Operation::ChangeLayer {
+					page: *page,
+					index: *index,
+					before: before.clone(),
+					after: next_after.clone(),
+				})
+			}
+			_ => None,
+		}
+	}
+
+	/// Estimates the number of bytes that `self` occupies.
+	pub fn memory_size(&self) -> usize {
+		let lines_size = |lines: &[Stroke]| {
+			lines.iter().map(|line| line.points.len()).sum::<usize>() * size_of::<Drawpoint>()
+		};
+		let images_size = |images: &[Image]| {
+			images
+				.iter()
+				.map(|image| size_of::<Image>() + image.data.len())
+				.sum::<usize>()
+		};
+		let layer_size = |layer: &Layer| {
+			size_of::<Layer>() + lines_size(layer.lines()) + images_size(&layer.images)
+		};
+		size_of::<Self>()
+			+ match self {
+				Operation::AddStroke { stroke, .. } => stroke.points.len() * size_of::<Drawpoint>(),
+				Operation::ReplaceLines {
+					removed, inserted, ..
+				} => lines_size(removed) + lines_size(inserted),
+				Operation::MoveElements { lines, images, .. } => {
+					(lines.len() + images.len()) * size_of::<ElementId>()
+				}
+				Operation::AddPage { page, .. } | Operation::RemovePage { page, .. } => {
+					page.layers.iter().map(layer_size).sum()
+				}
+				Operation::ReplacePage { before, after, .. } => before
+					.layers
+					.iter()
+					.chain(after.layers.iter())
+					.map(layer_size)
+					.sum(),
+				Operation::MovePage { .. } | Operation::MoveLayer { .. } => 0,
+				Operation::AddImage { image, .. } => images_size(std::slice::from_ref(image)),
+				Operation::AddLayer { layer, .. } | Operation::RemoveLayer { layer, .. } => {
+					layer_size(layer)
+				}
+				Operation::ChangeLayer { before, after, .. } => {
+					before.name.len() + after.name.len()
+				}
+				Operation::Group(operations) => operations.iter().map(Operation::memory_size).sum(),
+			}
+	}
+
+	/// Collects all images that are stored in `self`.
+	pub fn images_mut(&mut self) -> Vec<&mut Image> {
+		match self {
+			Operation::AddPage { page, .. } | Operation::RemovePage { page, .. } => {
+				page.images_mut().collect()
+			}
+			Operation::ReplacePage { before, after, .. } => {
+				before.images_mut().chain(after.images_mut()).collect()
+			}
+			Operation::AddImage { image, .. } => vec![image],
+			Operation::AddLayer { layer, .. } | Operation::RemoveLayer { layer, .. } => {
+				layer.images.iter_mut().collect()
+			}
+			Operation::Group(operations) => operations
+				.iter_mut()
+				.flat_map(Operation::images_mut)
+				.collect(),
+			_ => Vec::new(),
+		}
+	}
+
+	/// Collects all images that are stored in `self`.
+	pub fn images(&self) -> Vec<&Image> {
+		match self {
+			Operation::AddPage { page, .. } | Operation::RemovePage { page, .. } => {
+				page.images().collect()
+			}
+			Operation::ReplacePage { before, after, .. } => {
+				before.images().chain(after.images()).collect()
+			}
+			Operation::AddImage { image, .. } => vec![image],
+			Operation::AddLayer { layer, .. } | Operation::RemoveLayer { layer, .. } => {
+				layer.images.iter().collect()
+			}
+			Operation::Group(operations) => operations.iter().flat_map(Operation::images).collect(),
+			_ => Vec::new(),
+		}
+	}
+}
+
+/// Which of the name, visibility, lock and opacity differ between `before` and `after`.
+fn changed_properties(before: &LayerProperties, after: &LayerProperties) -> [bool; 4] {
+	[
+		before.name != after.name,
+		before.visible != after.visible,
+		before.locked != after.locked,
+		before.opacity != after.opacity,
+	]
+}
+
+/// Removes the layer at `index` of `page`.
+///
+/// The active layer stays the same, if it was removed the layer below becomes active.
+fn remove_layer(page: &mut Page, index: usize) {
+	let active = page.active_layer_index();
+	page.layers.remove(index);
+	page.active_layer = if active > index || (active == index && index > 0) {
+		active - 1
+	} else {
+		active
+	};
+}
+
+/// Moves the layer at `from` of `page` to `to`, the active layer stays the same.
+fn move_layer(page: &mut Page, from: usize, to: usize) {
+	let active = page.layers[page.active_layer_index()].id;
+	let layer = page.layers.remove(from);
+	page.layers.insert(to, layer);
+	page.active_layer = page
+		.layers
+		.iter()
+		.position(|layer| layer.id == active)
+		.unwrap_or(0);
+}
+
+/// Identifier of an `Entry`, unique within its `History`.
+pub type EntryId = usize;
+
+/// An `Operation` in the `History` together with the page that was viewed before it was applied.
+///
+/// Entries form a tree, the `parent` is the entry that was applied before, `children` are the alternatives that were applied afterwards.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Entry {
+	pub operation: Operation,
+	pub viewed_page: usize,
+	/// Seconds since the UNIX epoch when the operation was recorded, 0 if unknown.
+	pub timestamp: u64,
+	parent: Option<EntryId>,
+	/// Ordered by their last visit, the last child is the one that `History::redo()` applies.
+	children: Vec<EntryId>,
+}
+
+impl Entry {
+	/// The entry that was applied before `self`, `None` if `self` was applied to the oldest stored state.
+	pub fn parent(&self) -> Option<EntryId> {
+		self.parent
+	}
+
+	fn memory_size(&self) -> usize {
+		self.operation.memory_size()
+	}
+}
+
+/// Undo tree of `Operation`s.
+///
+/// Recording an operation after undoing starts a new branch, the undone operations stay available on their own branch.
+/// The oldest operations are dropped once more than `max_depth` operations can be undone
+/// or the stored operations occupy more than `max_memory` bytes.
+/// Only the tree is serialized, the limits have to be set with `set_limits()` after deserializing.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct History {
+	entries: BTreeMap<EntryId, Entry>,
+	/// Entries that were applied to the oldest stored state, ordered by their last visit.
+	roots: Vec<EntryId>,
+	/// The newest applied entry, `None` if the pages are in the oldest stored state.
+	current: Option<EntryId>,
+	next_id: EntryId,
+	#[serde(skip)]
+	memory: usize,
+	#[serde(skip, default = "unlimited")]
+	max_depth: usize,
+	#[serde(skip, default = "unlimited")]
+	max_memory: usize,
+}
+
+fn unlimited() -> usize {
+	usize::MAX
+}
+
+impl History {
+	pub fn new(max_depth: usize, max_memory: usize) -> Self {
+		Self {
+			entries: BTreeMap::new(),
+			roots: Vec::new(),
+			current: None,
+			next_id: 0,
+			memory: 0,
+			max_depth,
+			max_memory,
+		}
+	}
+
+	/// Records `operation`, which has already been applied to the pages while `viewed_page` was shown.
+	///
+	/// If operations were undone before, `operation` starts a new branch next to them.
+	pub fn push(&mut self, operation: Operation, viewed_page: usize) {
+		let id = self.next_id;
+		self.next_id += 1;
+		let entry = Entry {
+			operation,
+			viewed_page,
+			timestamp: unix_time(),
+			parent: self.current,
+			children: Vec::new(),
+		};
+		self.memory += entry.memory_size();
+		self.entries.insert(id, entry);
+		self.children_mut(self.current).push(id);
+		self.current = Some(id);
+		self.enforce_limits();
+	}
+
+	/// Records `operation` like `push()`, but combines it with the current entry if both change the same properties of the same layer.
+	///
+	/// Stepping a layer property through several values, e.g. its opacity, leaves a single entry.
+	/// The current entry is only combined while no operation can be redone after it, it is dropped if the combination changes nothing.
+	pub fn push_merged(&mut self, operation: Operation, viewed_page: usize) {
+		let merged = self.current.and_then(|id| {
+			let entry = &self.entries[&id];
+			if !entry.children.is_empty() {
+				return None;
+			}
+			Some((id, entry.operation.merged(&operation)?))
+		});
+		let (id, merged) = match merged {
+			Some(merged) => merged,
+			None => return self.push(operation, viewed_page),
+		};
+		let entry = self.entries.remove(&id).unwrap();
+		self.memory -= entry.memory_size();
+		self.children_mut(entry.parent).retain(|child| *child != id);
+		self.current = entry.parent;
+		if !matches!(merged, Operation::ChangeLayer { ref before, ref after, .. } if before == after)
+		{
+			self.push(merged, entry.viewed_page);
+		}
+	}
+
+	/// Reverts the newest applied operation on `pages`.
+	///
+	/// Returns the index of the page that was viewed before the operation, or `None` if there is nothing to undo.
+	pub fn undo(&mut self, pages: &mut Vec<Page>) -> Option<usize> {
+		let entry = &self.entries[&self.current?];
+		entry.operation.revert(pages);
+		self.current = entry.parent;
+		Some(entry.viewed_page)
+	}
+
+	/// Applies the most recently visited operation after the current state on `pages` again.
+	///
+	/// Returns the index of the page that shows the change, or `None` if there is nothing to redo.
+	pub fn redo(&mut self, pages: &mut Vec<Page>) -> Option<usize> {
+		let id = *self.children(self.current).last()?;
+		let page = self.entries[&id].operation.apply(pages);
+		self.current = Some(id);
+		Some(page)
+	}
+
+	/// Reverts and applies operations on `pages` until the state after `target` is reached.
+	///
+	/// `target` `None` is the oldest stored state. The branch leading to `target` becomes the one that `redo()` follows.
+	/// Returns the index of the page that shows the last step, or `None` if `target` is unknown or already the current state.
+	pub fn jump(&mut self, pages: &mut Vec<Page>, target: Option<EntryId>) -> Option<usize> {
+		if target == self.current || matches!(target, Some(id) if !self.entries.contains_key(&id)) {
+			return None;
+		}
+		let mut reverted = self.ancestors(self.current);
+		let mut applied = self.ancestors(target);
+		while !reverted.is_empty() && reverted.last() == applied.last() {
+			reverted.pop();
+			applied.pop();
+		}
+		let mut page = None;
+		for id in reverted {
+			let entry = &self.entries[&id];
+			entry.operation.revert(pages);
+			page = Some(entry.viewed_page);
+		}
+		for id in applied.into_iter().rev() {
+			let children = self.children_mut(self.entries[&id].parent);
+			children.retain(|child| *child != id);
+			children.push(id);
+			page = Some(self.entries[&id].operation.apply(pages));
+		}
+		self.current = target;
+		page
+	}
+
+	/// Removes all operations.
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.roots.clear();
+		self.current = None;
+		self.memory = 0;
+	}
+
+	/// Changes the limits and drops the oldest operations that exceed them.
+	pub fn set_limits(&mut self, max_depth: usize, max_memory: usize) {
+		self.max_depth = max_depth;
+		self.max_memory = max_memory;
+		self.memory = self.entries.values().map(|entry| entry.memory_size()).sum();
+		self.enforce_limits();
+	}
+
+	/// Creates a copy of `self` for storing it in a document.
+	///
+	/// Only the newest `depth` operations that lead to the current state are kept,
+	/// other branches and the operations that can be redone are dropped.
+	pub fn compacted(&self, depth: usize) -> Self {
+		let mut history = Self::new(self.max_depth, self.max_memory);
+		history.next_id = self.next_id;
+		let mut path = self.ancestors(self.current);
+		path.truncate(depth);
+		let mut parent = None;
+		for id in path.into_iter().rev() {
+			let mut entry = self.entries[&id].clone();
+			entry.parent = parent;
+			entry.children.clear();
+			history.memory += entry.memory_size();
+			history.entries.insert(id, entry);
+			history.children_mut(parent).push(id);
+			parent = Some(id);
+		}
+		history.current = parent;
+		history
+	}
+
+	/// The newest applied entry, `None` if the pages are in the oldest stored state.
+	pub fn current(&self) -> Option<EntryId> {
+		self.current
+	}
+
+	/// The entry with `id`, if it is stored.
+	pub fn entry(&self, id: EntryId) -> Option<&Entry> {
+		self.entries.get(&id)
+	}
+
+	/// Lists the entries that are applied in the current state, starting with the newest.
+	pub fn applied(&self) -> Vec<EntryId> {
+		self.ancestors(self.current)
+	}
+
+	/// Lists all entries in tree order together with their branch level.
+	///
+	/// The oldest child of an entry continues its branch, every later child opens a branch one level deeper.
+	pub fn tree(&self) -> Vec<(EntryId, usize)> {
+		let mut tree = Vec::with_capacity(self.entries.len());
+		let mut stack = Self::sorted_branches(&self.roots, 0);
+		while let Some((id, level)) = stack.pop() {
+			tree.push((id, level));
+			stack.extend(Self::sorted_branches(&self.entries[&id].children, level));
+		}
+		tree
+	}
+
+	/// Calls `visit` with the pages after each entry and the index of the page that the entry changed.
+	///
+	/// `pages` is the current state, the states of all entries are derived from a copy of it.
+	pub fn visit_states(&self, pages: &[Page], mut visit: impl FnMut(EntryId, &[Page], usize)) {
+		let mut pages = pages.to_vec();
+		for id in self.ancestors(self.current) {
+			self.entries[&id].operation.revert(&mut pages);
+		}
+		let mut stack: Vec<(EntryId, bool)> =
+			self.roots.iter().rev().map(|id| (*id, true)).collect();
+		while let Some((id, entering)) = stack.pop() {
+			let entry = &self.entries[&id];
+			if entering {
+				let page = entry.operation.apply(&mut pages);
+				visit(id, &pages, page);
+				stack.push((id, false));
+				stack.extend(entry.children.iter().rev().map(|child| (*child, true)));
+			} else {
+				entry.operation.revert(&mut pages);
+			}
+		}
+	}
+
+	/// Collects all images that are stored in the operations of `self`.
+	pub fn images(&self) -> Vec<&Image> {
+		self.entries
+			.values()
+			.flat_map(|entry| entry.operation.images())
+			.collect()
+	}
+
+	/// Collects all images that are stored in the operations of `self`.
+	pub fn images_mut(&mut self) -> Vec<&mut Image> {
+		self.entries
+			.values_mut()
+			.flat_map(|entry| entry.operation.images_mut())
+			.collect()
+	}
+
+	/// Estimated number of bytes occupied by all stored operations.
+	pub fn memory(&self) -> usize {
+		self.memory
+	}
+
+	/// Orders `children` for `tree()`, the oldest child is popped from the returned stack first.
+	fn sorted_branches(children: &[EntryId], level: usize) -> Vec<(EntryId, usize)> {
+		let mut children = children.to_vec();
+		children.sort_unstable();
+		children
+			.into_iter()
+			.enumerate()
+			.map(|(index, id)| (id, if index == 0 { level } else { level + 1 }))
+			.rev()
+			.collect()
+	}
+
+	/// Lists `id` and all entries that were applied before it, starting with `id`.
+	fn ancestors(&self, id: Option<EntryId>) -> Vec<EntryId> {
+		let mut ancestors = Vec::new();
+		let mut next = id;
+		while let Some(id) = next {
+			ancestors.push(id);
+			next = self.entries[&id].parent;
+		}
+		ancestors
+	}
+
+	fn children(&self, parent: Option<EntryId>) -> &[EntryId] {
+		match parent {
+			Some(id) => &self.entries[&id].children,
+			None => &self.roots,
+		}
+	}
+
+	fn children_mut(&mut self, parent: Option<EntryId>) -> &mut Vec<EntryId> {
+		match parent {
+			Some(id) => &mut self.entries.get_mut(&id).unwrap().children,
+			None => &mut self.roots,
+		}
+	}
+
+	/// Drops the oldest operations until the limits are met.
+	///
+	/// The oldest applied operation is dropped together with all branches that start before it.
+	/// If that is the current entry, the operations that could be redone after it are dropped as well
+	/// and the current state becomes the oldest stored state.
+	/// Operations that can only be redone are dropped for the memory limit once no applied operations are left.
+	fn enforce_limits(&mut self) {
+		let mut path = self.ancestors(self.current);
+		while path.len() > self.max_depth || (self.memory > self.max_memory && !path.is_empty()) {
+			let oldest = path.pop().unwrap();
+			for root in std::mem::take(&mut self.roots) {
+				if root != oldest {
+					self.remove_branch(root);
+				}
+			}
+			if path.is_empty() {
+				self.remove_branch(oldest);
+				self.current = None;
+				break;
+			}
+			let entry = self.entries.remove(&oldest).unwrap();
+			self.memory -= entry.memory_size();
+			for child in entry.children.iter() {
+				self.entries.get_mut(child).unwrap().parent = None;
+			}
+			self.roots = entry.children;
+		}
+		while self.memory > self.max_memory && !self.roots.is_empty() {
+			let root = self.roots.remove(0);
+			self.remove_branch(root);
+		}
+	}
+
+	/// Removes the entry `id` and everything that was applied after it.
+	fn remove_branch(&mut self, id: EntryId) {
+		let mut stack = vec![id];
+		while let Some(id) = stack.pop() {
+			let entry = self.entries.remove(&id).unwrap();
+			self.memory -= entry.memory_size();
+			stack.extend(entry.children);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Appends a page to `pages` and returns the operation that records it.
+	fn add_page(pages: &mut Vec<Page>) -> Operation {
+		let operation = Operation::AddPage {
+			index: pages.len(),
+			page: Page::new(),
+		};
+		operation.apply(pages);
+		operation
+	}
+
+	fn stroke(points: &[(f64, f64)]) -> Stroke {
+		Stroke::new(
+			points
+				.iter()
+				.map(|position| Drawpoint::new(*position, 2.0, [0.0, 0.0, 0.0, 1.0]))
+				.collect(),
+		)
+	}
+
+	fn serialized(pages: &[Page]) -> serde_json::Value {
+		serde_json::to_value(pages).unwrap()
+	}
+
+	/// Checks that `operation` turns `before` into `after` and back.
+	fn assert_reversible(before: &Page, operation: &Operation, after: &Page) {
+		let mut pages = vec![before.clone(), Page::new()];
+		assert_eq!(operation.apply(&mut pages), 0);
+		assert_eq!(
+			serialized(&pages[..1]),
+			serialized(std::slice::from_ref(after))
+		);
+		assert_eq!(operation.revert(&mut pages), 0);
+		assert_eq!(
+			serialized(&pages[..1]),
+			serialized(std::slice::from_ref(before))
+		);
+	}
+
+	#[test]
+	fn unchanged_lines_have_no_operation() {
+		let lines = vec![stroke(&[(0.0, 0.0)])];
+		assert!(Operation::replace_lines(0, 0, 0, &lines, &lines.clone()).is_none());
+	}
+
+	#[test]
+	fn added_strokes_are_reversible() {
+		let mut before = Page::new();
+		before.layers[0].push_line(stroke(&[(0.0, 0.0)]));
+		let mut after = before.clone();
+		after.layers[0].push_line(stroke(&[(1.0, 1.0), (2.0, 2.0)]));
+		let operation =
+			Operation::replace_lines(0, 0, 0, before.layers[0].lines(), after.layers[0].lines())
+				.unwrap();
+		assert!(matches!(operation, Operation::AddStroke { index: 1, .. }));
+		assert_reversible(&before, &operation, &after);
+	}
+
+	#[test]
+	fn erased_lines_are_reversible() {
+		let mut before = Page::new();
+		for x in 0..4 {
+			before.layers[0].push_line(stroke(&[(x as f64, 0.0), (x as f64, 1.0)]));
+		}
+		let mut after = before.clone();
+		let split = after.layers[0].line_mut(2).split_off(1);
+		after.layers[0].splice_lines(3..3, vec![split]);
+		after.layers[0].splice_lines(1..2, Vec::new());
+		let operation =
+			Operation::replace_lines(0, 0, 0, before.layers[0].lines(), after.layers[0].lines())
+				.unwrap();
+		assert!(matches!(
+			operation,
+			Operation::ReplaceLines { index: 1, ref removed, ref inserted, .. }
+				if removed.len() == 2 && inserted.len() == 2
+		));
+		assert_reversible(&before, &operation, &after);
+	}
+
+	#[test]
+	fn translations_are_reversible() {
+		let mut before = Page::new();
+		before.layers[0].push_line(stroke(&[(0.0, 0.0), (1.0, 1.0)]));
+		before.layers[0].push_line(stroke(&[(5.0, 5.0)]));
+		let mut after = before.clone();
+		let id = before.layers[0].lines()[1].id;
+		after.layers[0].translate(&[id], &[], (2.0, 3.0));
+		let operation = Operation::MoveElements {
+			page: 0,
+			layer: 0,
+			lines: vec![id],
+			images: Vec::new(),
+			vector: (2.0, 3.0),
+		};
+		assert_reversible(&before, &operation, &after);
+	}
+
+	#[test]
+	fn layer_changes_are_reversible() {
+		let before = Page::new();
+		let mut after = before.clone();
+		after.layers[0].properties.opacity = 0.5;
+		let operation = Operation::ChangeLayer {
+			page: 0,
+			index: 0,
+			before: before.layers[0].properties.clone(),
+			after: after.layers[0].properties.clone(),
+		};
+		assert_reversible(&before, &operation, &after);
+	}
+
+	#[test]
+	fn consecutive_layer_changes_are_merged() {
+		let mut layer = Layer::new("Layer 1");
+		let mut history = History::new(10, usize::MAX);
+		let mut change = |history: &mut History, change: &dyn Fn(&mut LayerProperties)| {
+			let before = layer.properties.clone();
+			change(&mut layer.properties);
+			let after = layer.properties.clone();
+			history.push_merged(
+				Operation::ChangeLayer {
+					page: 0,
+					index: 0,
+					before,
+					after,
+				},
+				0,
+			);
+		};
+		change(&mut history, &|properties| properties.opacity = 0.9);
+		change(&mut history, &|properties| properties.opacity = 0.8);
+		assert_eq!(history.tree().len(), 1);
+		change(&mut history, &|properties| properties.visible = false);
+		change(&mut history, &|properties| properties.visible = true);
+		assert_eq!(history.tree().len(), 1);
+		change(&mut history, &|properties| properties.locked = true);
+		assert_eq!(history.tree().len(), 2);
+		let mut pages = vec![Page::new()];
+		pages[0].layers[0].properties.opacity = 0.8;
+		pages[0].layers[0].properties.locked = true;
+		history.undo(&mut pages);
+		history.undo(&mut pages);
+		assert_eq!(pages[0].layers[0].properties.opacity, 1.0);
+		assert!(!pages[0].layers[0].properties.locked);
+	}
+
+	#[test]
+	fn page_operations_are_reversible() {
+		let mut pages = vec![Page::new(), Page::new()];
+		pages[1].layers[0].push_line(stroke(&[(0.0, 0.0)]));
+		let before = serialized(&pages);
+		let operations = vec![
+			Operation::AddPage {
+				index: 1,
+				page: Page::new(),
+			},
+			Operation::MovePage { from: 2, to: 0 },
+			Operation::RemovePage {
+				index: 1,
+				page: pages[0].clone(),
+			},
+		];
+		for operation in operations.iter() {
+			operation.apply(&mut pages);
+		}
+		assert_eq!(pages.len(), 2);
+		assert_eq!(pages[0].layers[0].lines().len(), 1);
+		Operation::Group(operations).revert(&mut pages);
+		assert_eq!(serialized(&pages), before);
+	}
+
+	#[test]
+	fn depth_0_keeps_no_operations() {
+		let mut pages = vec![Page::new()];
+		let mut history = History::new(0, usize::MAX);
+		history.push(add_page(&mut pages), 0);
+		assert_eq!(history.current(), None);
+		assert!(history.tree().is_empty());
+		assert_eq!(history.memory(), 0);
+		assert_eq!(history.undo(&mut pages), None);
+		history.push(add_page(&mut pages), 0);
+		assert_eq!(history.current(), None);
+		assert_eq!(pages.len(), 3);
+	}
+
+	#[test]
+	fn depth_1_keeps_the_newest_operation() {
+		let mut pages = vec![Page::new()];
+		let mut history = History::new(1, usize::MAX);
+		history.push(add_page(&mut pages), 0);
+		history.push(add_page(&mut pages), 1);
+		assert_eq!(history.applied(), vec![1]);
+		assert_eq!(history.undo(&mut pages), Some(1));
+		assert_eq!(pages.len(), 2);
+		assert_eq!(history.undo(&mut pages), None);
+		assert_eq!(history.redo(&mut pages), Some(2));
+		assert_eq!(pages.len(), 3);
+	}
+
+	#[test]
+	fn operation_larger_than_max_memory_is_dropped() {
+		let mut pages = vec![Page::new()];
+		let mut history = History::new(10, 1);
+		history.push(add_page(&mut pages), 0);
+		assert_eq!(history.current(), None);
+		assert!(history.tree().is_empty());
+		assert_eq!(history.memory(), 0);
+		history.push(add_page(&mut pages), 0);
+		assert_eq!(history.undo(&mut pages), None);
+		assert_eq!(pages.len(), 3);
+	}
+
+	#[test]
+	fn dropping_the_current_entry_drops_its_redo_branch() {
+		let mut pages = vec![Page::new()];
+		let mut history = History::new(10, usize::MAX);
+		history.push(add_page(&mut pages), 0);
+		history.push(add_page(&mut pages), 1);
+		history.undo(&mut pages);
+		history.set_limits(0, usize::MAX);
+		assert_eq!(history.current(), None);
+		assert!(history.tree().is_empty());
+		assert_eq!(history.memory(), 0);
+		assert_eq!(history.redo(&mut pages), None);
+	}
+}
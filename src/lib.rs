@@ -0,0 +1,14 @@
+//! Document model and tool engine of the handwritten notetaking editor.
+//!
+//! Nothing in here depends on GTK: pages, images, the `.hnote` file format and all `DrawTool`s work on plain data,
+//! so they can be used by other front ends, tests and benchmarks.
+pub mod bundle;
+pub mod document;
+pub mod error;
+pub mod history;
+pub mod model;
+pub mod outline;
+pub mod pressure;
+pub mod render_options;
+pub mod spatial;
+pub mod tools;
@@ -1,10 +1,19 @@
-pub mod application;
-pub mod datatypes;
-use crate::application::Application;
+pub mod editor;
+use crate::editor::application::Application;
+use crate::editor::cli;
 use gio::prelude::*;
 use std::env::args;
 
 fn main() {
+	let arguments = args().collect::<Vec<_>>();
+	if arguments.get(1).map(String::as_str) == Some("render") {
+		if let Err(error) = cli::render(&arguments[2..]) {
+			eprintln!("{}", error);
+			std::process::exit(1);
+		}
+		return;
+	}
+
 	let application =
 		gtk::Application::new(Some("com.github.gtk-rs.examples.basic"), Default::default())
 			.expect("Initialization failed...");
@@ -13,5 +22,5 @@ fn main() {
 		Application::new(app);
 	});
 
-	application.run(&args().collect::<Vec<_>>());
+	application.run(&arguments);
 }
@@ -0,0 +1,371 @@
+use crate::bundle::content_hash;
+use crate::spatial::SpatialIndex;
+use crate::tools::CurrentDrawTool;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Half edge length of the square around a position that is searched first for the closest point.
+const SEARCH_DISTANCE: f64 = 64.0;
+
+/// Identifier of a stroke or an image that stays the same for its whole lifetime.
+///
+/// Identifiers are chosen randomly, so they are unique within a document and across documents.
+pub type ElementId = u64;
+
+/// Creates a new random `ElementId`.
+pub fn new_element_id() -> ElementId {
+	rand::random()
+}
+
+/// Seconds since the UNIX epoch, as stored in timestamps.
+pub fn unix_time() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |duration| duration.as_secs())
+}
+
+/// Serializable page datatype that contains the `layers` of a page, ordered from bottom to top.
+///
+/// A page always has at least one layer, `active_layer` is the index of the layer that tools work on.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Page {
+	pub layers: Vec<Layer>,
+	pub active_layer: usize,
+}
+
+impl Default for Page {
+	fn default() -> Self {
+		Self {
+			layers: vec![Layer::new("Layer 1")],
+			active_layer: 0,
+		}
+	}
+}
+
+impl Page {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Index of the layer that tools work on, clamped to the existing layers.
+	pub fn active_layer_index(&self) -> usize {
+		self.active_layer.min(self.layers.len() - 1)
+	}
+
+	/// The active layer, if it can be edited.
+	///
+	/// Hidden and locked layers can not be edited, so tools ignore them.
+	pub fn editable_layer_mut(&mut self) -> Option<&mut Layer> {
+		let index = self.active_layer_index();
+		let layer = &mut self.layers[index];
+		if layer.properties.visible && !layer.properties.locked {
+			Some(layer)
+		} else {
+			None
+		}
+	}
+
+	/// Iterates over the images of all layers.
+	pub fn images(&self) -> impl Iterator<Item = &Image> {
+		self.layers.iter().flat_map(|layer| layer.images.iter())
+	}
+
+	/// Iterates over the images of all layers.
+	pub fn images_mut(&mut self) -> impl Iterator<Item = &mut Image> {
+		self.layers
+			.iter_mut()
+			.flat_map(|layer| layer.images.iter_mut())
+	}
+}
+
+/// Serializable layer datatype that contains `lines` and `images` which are drawn together.
+///
+/// The images of a layer are drawn beneath its lines.
+/// The points of `lines` are kept in a `SpatialIndex` that is updated whenever the lines are searched by position.
+/// The lines can only be changed through the methods of `Layer`, so the index learns which lines changed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Layer {
+	pub id: ElementId,
+	pub properties: LayerProperties,
+	lines: Vec<Stroke>,
+	pub images: Vec<Image>,
+	#[serde(skip)]
+	index: SpatialIndex,
+}
+
+impl Layer {
+	/// Creates an empty, visible and unlocked layer called `name`.
+	pub fn new(name: &str) -> Self {
+		Self {
+			id: new_element_id(),
+			properties: LayerProperties {
+				name: name.to_string(),
+				visible: true,
+				locked: false,
+				opacity: 1.0,
+			},
+			lines: Vec::new(),
+			images: Vec::new(),
+			index: SpatialIndex::default(),
+		}
+	}
+
+	/// The lines of `self`, drawn in their order.
+	pub fn lines(&self) -> &[Stroke] {
+		&self.lines
+	}
+
+	/// Adds `line` on top of the other lines.
+	pub fn push_line(&mut self, line: Stroke) {
+		self.lines.push(line);
+	}
+
+	/// Appends `point` to the topmost line, which has to exist.
+	pub fn push_point(&mut self, point: Drawpoint) {
+		self.lines.last_mut().unwrap().points.push(point);
+	}
+
+	/// The line at `index` for changing its points, it is indexed again on the next search.
+	pub fn line_mut(&mut self, index: usize) -> &mut Stroke {
+		self.index.mark_changed(self.lines[index].id);
+		&mut self.lines[index]
+	}
+
+	/// Replaces the lines in `range` with `inserted` and returns the removed lines.
+	pub fn splice_lines(&mut self, range: Range<usize>, inserted: Vec<Stroke>) -> Vec<Stroke> {
+		for line in inserted.iter() {
+			self.index.mark_changed(line.id);
+		}
+		self.lines.splice(range, inserted).collect()
+	}
+
+	/// Moves the lines with the ids `lines` and the images with the ids `images` by `vector`.
+	///
+	/// Ids that are not part of `self` are skipped.
+	pub fn translate(&mut self, lines: &[ElementId], images: &[ElementId], vector: (f64, f64)) {
+		let lines: HashSet<ElementId> = lines.iter().copied().collect();
+		for line_index in 0..self.lines.len() {
+			if !lines.contains(&self.lines[line_index].id) {
+				continue;
+			}
+			for point in self.line_mut(line_index).points.iter_mut() {
+				point.position.0 += vector.0;
+				point.position.1 += vector.1;
+			}
+		}
+		for image in self.images.iter_mut() {
+			if images.contains(&image.id) {
+				image.position.0 += vector.0;
+				image.position.1 += vector.1;
+			}
+		}
+	}
+
+	/// Index of the line with `id`.
+	pub fn line_index(&self, id: ElementId) -> Option<usize> {
+		self.lines.iter().position(|line| line.id == id)
+	}
+
+	/// Index of the image with `id`.
+	pub fn image_index(&self, id: ElementId) -> Option<usize> {
+		self.images.iter().position(|image| image.id == id)
+	}
+
+	/// Indices of the lines that may have points in `rectangle` (`[left, top, right, bottom]`), in ascending order.
+	///
+	/// Lines with points in `rectangle` are always included, the points still have to be checked by the caller.
+	pub fn lines_in_rectangle(&mut self, rectangle: [f64; 4]) -> Vec<usize> {
+		self.index.sync(&self.lines);
+		self.index.query(rectangle)
+	}
+
+	/// Indices of the lines that may have points closer than `distance` to `position`, in ascending order.
+	pub fn lines_near(&mut self, position: (f64, f64), distance: f64) -> Vec<usize> {
+		self.lines_in_rectangle([
+			position.0 - distance,
+			position.1 - distance,
+			position.0 + distance,
+			position.1 + distance,
+		])
+	}
+
+	/// Finds the point of all `lines` that is closest to `position`.
+	///
+	/// Returns the index of its line and its position, `None` if there are no points.
+	/// The search starts around `position` and is widened until the closest point is certain.
+	pub fn closest_line_position(&mut self, position: (f64, f64)) -> Option<(usize, (f64, f64))> {
+		let mut distance = SEARCH_DISTANCE;
+		loop {
+			let candidates = self.lines_near(position, distance);
+			let mut lowest_distance = f64::INFINITY;
+			let mut closest = None;
+			for i in candidates {
+				for point in self.lines[i].points.iter() {
+					let point_distance = ((point.position.0 - position.0).powf(2.0)
+						+ (point.position.1 - position.1).powf(2.0))
+					.sqrt();
+					if point_distance < lowest_distance {
+						lowest_distance = point_distance;
+						closest = Some((i, point.position));
+					}
+				}
+			}
+			let searched_all = self.index.covers([
+				position.0 - distance,
+				position.1 - distance,
+				position.0 + distance,
+				position.1 + distance,
+			]);
+			match closest {
+				Some(closest) if lowest_distance <= distance || searched_all => {
+					return Some(closest)
+				}
+				None if searched_all => return None,
+				_ => distance *= 2.0,
+			}
+		}
+	}
+}
+
+/// Serializable properties of a layer that can be changed in the layer panel.
+///
+/// `opacity` ranges from 0 (invisible) to 1 (opaque) and is applied to the layer as a whole.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LayerProperties {
+	pub name: String,
+	pub visible: bool,
+	pub locked: bool,
+	pub opacity: f64,
+}
+
+/// Serializable line datatype, the `points` are connected in their order.
+///
+/// `created` holds the seconds since the UNIX epoch when the stroke was started, 0 if unknown.
+/// `tool` is the tool that drew the stroke, `None` if unknown.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Stroke {
+	pub id: ElementId,
+	pub points: Vec<Drawpoint>,
+	pub created: u64,
+	pub tool: Option<CurrentDrawTool>,
+	pub style: StrokeStyle,
+	pub label: Option<String>,
+}
+
+impl Stroke {
+	/// Creates a stroke of `points` with a new `id` and the default style.
+	pub fn new(points: Vec<Drawpoint>) -> Self {
+		Self {
+			id: new_element_id(),
+			points,
+			created: unix_time(),
+			tool: None,
+			style: StrokeStyle::default(),
+			label: None,
+		}
+	}
+
+	/// Creates an empty stroke that is drawn with `tool` in `style`.
+	pub fn drawn_with(tool: CurrentDrawTool, style: StrokeStyle) -> Self {
+		Self {
+			tool: Some(tool),
+			style,
+			..Self::new(Vec::new())
+		}
+	}
+
+	/// Splits the points from index `at` on into a new stroke with a new `id`.
+	///
+	/// All other properties are kept in both strokes.
+	pub fn split_off(&mut self, at: usize) -> Self {
+		Self {
+			id: new_element_id(),
+			points: self.points.split_off(at),
+			..self.clone()
+		}
+	}
+}
+
+/// Shape of the line ends of a stroke.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+	Butt,
+	Round,
+	Square,
+}
+
+/// Shape of the corners of a stroke.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+	Miter,
+	Round,
+	Bevel,
+}
+
+/// Serializable style that applies to a whole stroke.
+///
+/// `dash` alternates the lengths of drawn and skipped segments, an empty `dash` draws a solid line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+	pub cap: LineCap,
+	pub join: LineJoin,
+	pub dash: Vec<f64>,
+}
+
+impl Default for StrokeStyle {
+	fn default() -> Self {
+		Self {
+			cap: LineCap::Round,
+			join: LineJoin::Round,
+			dash: Vec::new(),
+		}
+	}
+}
+
+/// Serializable image datatype.
+///
+/// `path` is only kept as a reference to the imported file, the image is identified by the content `hash` of its `data`.
+/// `data` is not part of the JSON and is stored once per `hash` in the `.hnote` bundle.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Image {
+	pub id: ElementId,
+	pub path: PathBuf,
+	pub hash: String,
+	pub position: (f64, f64),
+	#[serde(skip)]
+	pub data: Rc<Vec<u8>>,
+}
+
+impl Image {
+	pub fn new(path: PathBuf, data: Vec<u8>, position: (f64, f64)) -> Self {
+		Self {
+			id: new_element_id(),
+			path,
+			hash: content_hash(&data),
+			position,
+			data: Rc::new(data),
+		}
+	}
+}
+
+/// Serializable point that can be drawn on the canvas in a `line`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Drawpoint {
+	pub position: (f64, f64),
+	pub line_width: f64,
+	pub rgba: [f64; 4],
+}
+
+impl Drawpoint {
+	pub fn new(position: (f64, f64), line_width: f64, rgba: [f64; 4]) -> Self {
+		Self {
+			position,
+			line_width,
+			rgba,
+		}
+	}
+}
@@ -0,0 +1,486 @@
+use crate::model::{LineCap, LineJoin, Stroke};
+use std::f64::consts::PI;
+
+/// Longest miter of a corner relative to the line width, longer miters are beveled like in cairo.
+pub const MITER_LIMIT: f64 = 10.0;
+
+/// Number of edges that approximate a half circle of a round cap or corner.
+const ARC_SEGMENTS: usize = 12;
+
+/// Filled polygon that covers a part of a stroke which is drawn in a single colour.
+///
+/// The polygon is closed from the last of its `points` back to the first.
+/// It may overlap itself where the stroke crosses itself, so it has to be filled with the nonzero rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outline {
+	pub points: Vec<(f64, f64)>,
+	pub rgba: [f64; 4],
+}
+
+/// Point on the center line of a stroke, `radius` is half of its line width.
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+	position: (f64, f64),
+	radius: f64,
+	rgba: [f64; 4],
+}
+
+/// Computes the polygons that cover `stroke`, in the style of perfect-freehand.
+///
+/// The outline follows the `line_width` of every `Drawpoint`, so the stroke tapers and swells where the width changes.
+/// Every segment has the `rgba` of the point it starts at, a new outline begins wherever the colour changes.
+/// Each dash of the `StrokeStyle` is outlined on its own with the caps of the style, sharp corners get its joins.
+/// A stroke of a single point becomes a dot, unless its caps are `LineCap::Butt`.
+pub fn stroke_outlines(stroke: &Stroke) -> Vec<Outline> {
+	let samples: Vec<Sample> = stroke
+		.points
+		.iter()
+		.map(|point| Sample {
+			position: point.position,
+			radius: point.line_width / 2.0,
+			rgba: point.rgba,
+		})
+		.collect();
+	let mut outlines = Vec::new();
+	for dash in dashes(&samples, &stroke.style.dash) {
+		let runs = colour_runs(&dash);
+		let run_count = runs.len();
+		for (i, run) in runs.into_iter().enumerate() {
+			let start_cap = if i == 0 {
+				stroke.style.cap
+			} else {
+				LineCap::Butt
+			};
+			let end_cap = if i + 1 == run_count {
+				stroke.style.cap
+			} else {
+				LineCap::Butt
+			};
+			let points = outline(&run, start_cap, end_cap, stroke.style.join);
+			if !points.is_empty() {
+				outlines.push(Outline {
+					points,
+					rgba: run[0].rgba,
+				});
+			}
+		}
+	}
+	outlines
+}
+
+/// Splits `samples` into the dashes of `pattern`, which alternates the lengths of drawn and skipped parts.
+///
+/// The cuts are interpolated between the samples. A dash of length 0 keeps a single sample.
+/// Patterns without a positive length or with a negative length are drawn solid.
+fn dashes(samples: &[Sample], pattern: &[f64]) -> Vec<Vec<Sample>> {
+	if samples.is_empty() {
+		return Vec::new();
+	}
+	if pattern.iter().sum::<f64>() <= 0.0 || pattern.iter().any(|length| *length < 0.0) {
+		return vec![samples.to_vec()];
+	}
+	let mut dashes = Vec::new();
+	let mut current = vec![samples[0]];
+	let mut is_drawn = true;
+	let mut index = 0;
+	let mut left = pattern[0];
+	for segment in samples.windows(2) {
+		let length = distance(segment[0].position, segment[1].position);
+		let mut travelled = 0.0;
+		while left <= length - travelled {
+			travelled += left;
+			let cut = interpolate(segment[0], segment[1], travelled / length.max(f64::EPSILON));
+			if is_drawn {
+				current.push(cut);
+				dashes.push(std::mem::take(&mut current));
+			} else {
+				current = vec![cut];
+			}
+			is_drawn = !is_drawn;
+			index = (index + 1) % pattern.len();
+			left = pattern[index];
+		}
+		left -= length - travelled;
+		if is_drawn {
+			current.push(segment[1]);
+		}
+	}
+	// A dash that only begins at the very end is left out, unless it is all there is.
+	let has_length = current
+		.iter()
+		.any(|sample| sample.position != current[0].position);
+	if is_drawn && (has_length || dashes.is_empty()) {
+		dashes.push(current);
+	}
+	dashes
+}
+
+/// Splits `samples` wherever the colour changes, the sample with the new colour ends one run and starts the next.
+///
+/// The colour of the last sample is never drawn, so it does not start a run.
+fn colour_runs(samples: &[Sample]) -> Vec<Vec<Sample>> {
+	let mut runs = Vec::new();
+	let mut start = 0;
+	for i in 1..samples.len().saturating_sub(1) {
+		if samples[i].rgba != samples[start].rgba {
+			runs.push(samples[start..=i].to_vec());
+			start = i;
+		}
+	}
+	runs.push(samples[start..].to_vec());
+	runs
+}
+
+/// Computes the polygon around `samples` with `start_cap` and `end_cap` at its ends and `join` at sharp corners.
+///
+/// Both sides are offset by the radius of every sample, the left side is walked forwards and the right side backwards.
+fn outline(
+	samples: &[Sample],
+	start_cap: LineCap,
+	end_cap: LineCap,
+	join: LineJoin,
+) -> Vec<(f64, f64)> {
+	let mut samples = samples.to_vec();
+	samples.dedup_by(|next, previous| next.position == previous.position);
+	if samples.len() == 1 {
+		return dot(samples[0], start_cap);
+	}
+	let directions: Vec<(f64, f64)> = samples
+		.windows(2)
+		.map(|segment| direction(segment[0].position, segment[1].position))
+		.collect();
+	let mut left = Vec::new();
+	let mut right = Vec::new();
+	for (i, sample) in samples.iter().enumerate() {
+		let incoming = directions[i.saturating_sub(1)];
+		let outgoing = directions.get(i).copied().unwrap_or(incoming);
+		if dot_product(incoming, outgoing) < 0.0 {
+			corner(*sample, incoming, outgoing, join, &mut left, &mut right);
+		} else {
+			// The offset is widened on bends, so both segments keep their width.
+			let bisector = normal(direction((0.0, 0.0), add(incoming, outgoing)));
+			let offset = sample.radius / dot_product(bisector, normal(incoming));
+			left.push(add(sample.position, scale(bisector, offset)));
+			right.push(add(sample.position, scale(bisector, -offset)));
+		}
+	}
+	let first = samples[0];
+	let last = samples[samples.len() - 1];
+	let mut polygon = left;
+	cap(
+		&mut polygon,
+		last,
+		directions[directions.len() - 1],
+		end_cap,
+	);
+	polygon.extend(right.into_iter().rev());
+	cap(&mut polygon, first, scale(directions[0], -1.0), start_cap);
+	polygon
+}
+
+/// Adds the corners of both sides at a `sample` where the stroke turns by more than a right angle.
+///
+/// The outer side gets `join`, the inner side is led through the center of the sample, like cairo does.
+fn corner(
+	sample: Sample,
+	incoming: (f64, f64),
+	outgoing: (f64, f64),
+	join: LineJoin,
+	left: &mut Vec<(f64, f64)>,
+	right: &mut Vec<(f64, f64)>,
+) {
+	let turns_left = incoming.0 * outgoing.1 - incoming.1 * outgoing.0 > 0.0;
+	for (side, sign, is_inner) in [(left, 1.0, turns_left), (right, -1.0, !turns_left)].iter_mut() {
+		let from = add(
+			sample.position,
+			scale(normal(incoming), *sign * sample.radius),
+		);
+		let to = add(
+			sample.position,
+			scale(normal(outgoing), *sign * sample.radius),
+		);
+		side.push(from);
+		if *is_inner {
+			side.push(sample.position);
+		} else {
+			match join {
+				LineJoin::Round => {
+					// A turn back on itself counts as a right turn, so the arc leads around the left side.
+					let turn = (incoming.0 * outgoing.1 - incoming.1 * outgoing.0)
+						.atan2(dot_product(incoming, outgoing))
+						.abs();
+					let turn = if turns_left { turn } else { -turn };
+					arc(side, sample, angle(scale(normal(incoming), *sign)), turn);
+				}
+				LineJoin::Miter => {
+					// The bisector is (0, 0) on a turn back on itself, which is always beveled.
+					let bisector = normal(direction((0.0, 0.0), add(incoming, outgoing)));
+					let cosine = dot_product(bisector, normal(incoming));
+					if cosine * MITER_LIMIT >= 1.0 {
+						let offset = *sign * sample.radius / cosine;
+						side.push(add(sample.position, scale(bisector, offset)));
+					}
+				}
+				LineJoin::Bevel => {}
+			}
+		}
+		side.push(to);
+	}
+}
+
+/// Adds the end of the stroke at `sample` that points in `direction`.
+///
+/// The points lead from the left to the right side, seen in `direction`, which are both already part of `polygon`.
+fn cap(polygon: &mut Vec<(f64, f64)>, sample: Sample, direction: (f64, f64), cap: LineCap) {
+	match cap {
+		LineCap::Butt => {}
+		LineCap::Round => arc(polygon, sample, angle(normal(direction)), -PI),
+		LineCap::Square => {
+			let ahead = add(sample.position, scale(direction, sample.radius));
+			polygon.push(add(ahead, scale(normal(direction), sample.radius)));
+			polygon.push(add(ahead, scale(normal(direction), -sample.radius)));
+		}
+	}
+}
+
+/// Outline of a stroke without length at `sample`: a circle for round caps, a square for square caps, nothing for butt caps.
+fn dot(sample: Sample, cap: LineCap) -> Vec<(f64, f64)> {
+	let (x, y) = sample.position;
+	let radius = sample.radius;
+	match cap {
+		LineCap::Butt => Vec::new(),
+		LineCap::Round => {
+			let mut circle = vec![(x + radius, y)];
+			arc(&mut circle, sample, 0.0, 2.0 * PI);
+			circle
+		}
+		LineCap::Square => vec![
+			(x - radius, y - radius),
+			(x + radius, y - radius),
+			(x + radius, y + radius),
+			(x - radius, y + radius),
+		],
+	}
+}
+
+/// Adds the points between the ends of the arc around `sample` that starts at `start` and covers `sweep` (both in radians).
+fn arc(points: &mut Vec<(f64, f64)>, sample: Sample, start: f64, sweep: f64) {
+	let segments = ((sweep.abs() / PI * ARC_SEGMENTS as f64).ceil() as usize).max(1);
+	for i in 1..segments {
+		let angle = start + sweep * i as f64 / segments as f64;
+		points.push((
+			sample.position.0 + sample.radius * angle.cos(),
+			sample.position.1 + sample.radius * angle.sin(),
+		));
+	}
+}
+
+/// The sample at `t` (0 to 1) between `from` and `to`, it keeps the colour of `from`.
+fn interpolate(from: Sample, to: Sample, t: f64) -> Sample {
+	Sample {
+		position: (
+			from.position.0 + (to.position.0 - from.position.0) * t,
+			from.position.1 + (to.position.1 - from.position.1) * t,
+		),
+		radius: from.radius + (to.radius - from.radius) * t,
+		rgba: from.rgba,
+	}
+}
+
+/// Unit vector from `from` to `to`, `(0, 0)` if both are the same.
+fn direction(from: (f64, f64), to: (f64, f64)) -> (f64, f64) {
+	let length = distance(from, to);
+	if length > 0.0 {
+		((to.0 - from.0) / length, (to.1 - from.1) / length)
+	} else {
+		(0.0, 0.0)
+	}
+}
+
+/// `vector` rotated by a right angle, towards the side of a stroke along `vector` that is called left in the outline.
+fn normal(vector: (f64, f64)) -> (f64, f64) {
+	(-vector.1, vector.0)
+}
+
+fn angle(vector: (f64, f64)) -> f64 {
+	vector.1.atan2(vector.0)
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+	(a.0 + b.0, a.1 + b.1)
+}
+
+fn scale(vector: (f64, f64), factor: f64) -> (f64, f64) {
+	(vector.0 * factor, vector.1 * factor)
+}
+
+fn dot_product(a: (f64, f64), b: (f64, f64)) -> f64 {
+	a.0 * b.0 + a.1 * b.1
+}
+
+fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+	((from.0 - to.0).powf(2.0) + (from.1 - to.1).powf(2.0)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::{Drawpoint, StrokeStyle};
+
+	const BLACK: [f64; 4] = [0.0, 0.0, 0.0, 1.0];
+	const RED: [f64; 4] = [1.0, 0.0, 0.0, 1.0];
+	const CAPS: [LineCap; 3] = [LineCap::Butt, LineCap::Round, LineCap::Square];
+	const JOINS: [LineJoin; 3] = [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel];
+
+	/// Stroke through `points` (position, line width, colour) with `cap`, round joins and `dash`.
+	fn stroke(points: &[((f64, f64), f64, [f64; 4])], cap: LineCap, dash: &[f64]) -> Stroke {
+		let mut stroke = Stroke::new(
+			points
+				.iter()
+				.map(|(position, line_width, rgba)| Drawpoint::new(*position, *line_width, *rgba))
+				.collect(),
+		);
+		stroke.style = StrokeStyle {
+			cap,
+			join: LineJoin::Round,
+			dash: dash.to_vec(),
+		};
+		stroke
+	}
+
+	/// Horizontal black stroke of width 2 from x = 0 to x = 100.
+	fn line(cap: LineCap, dash: &[f64]) -> Stroke {
+		stroke(
+			&[((0.0, 0.0), 2.0, BLACK), ((100.0, 0.0), 2.0, BLACK)],
+			cap,
+			dash,
+		)
+	}
+
+	fn center(outline: &Outline) -> (f64, f64) {
+		let count = outline.points.len() as f64;
+		let sum = outline
+			.points
+			.iter()
+			.fold((0.0, 0.0), |sum, point| add(sum, *point));
+		scale(sum, 1.0 / count)
+	}
+
+	#[test]
+	fn single_points_become_dots() {
+		let point = |cap| stroke_outlines(&stroke(&[((5.0, 5.0), 4.0, BLACK)], cap, &[]));
+		assert!(point(LineCap::Butt).is_empty());
+		let circle = point(LineCap::Round);
+		assert_eq!(circle.len(), 1);
+		assert_eq!(circle[0].points.len(), 2 * ARC_SEGMENTS);
+		assert!(circle[0]
+			.points
+			.iter()
+			.all(|point| (distance(*point, (5.0, 5.0)) - 2.0).abs() < 1e-9));
+		let square = point(LineCap::Square);
+		assert_eq!(square.len(), 1);
+		assert_eq!(
+			square[0].points,
+			vec![(3.0, 3.0), (7.0, 3.0), (7.0, 7.0), (3.0, 7.0)]
+		);
+	}
+
+	#[test]
+	fn colour_changes_split_the_outline() {
+		let outlines = stroke_outlines(&stroke(
+			&[
+				((0.0, 0.0), 2.0, BLACK),
+				((10.0, 0.0), 2.0, BLACK),
+				((20.0, 0.0), 2.0, RED),
+				((30.0, 0.0), 2.0, RED),
+			],
+			LineCap::Butt,
+			&[],
+		));
+		assert_eq!(outlines.len(), 2);
+		assert_eq!(outlines[0].rgba, BLACK);
+		assert_eq!(outlines[1].rgba, RED);
+		assert!(outlines[0].points.iter().all(|point| point.0 <= 20.0));
+		assert!(outlines[1].points.iter().all(|point| point.0 >= 20.0));
+		// The colour of the last point is not drawn.
+		let outlines = stroke_outlines(&stroke(
+			&[((0.0, 0.0), 2.0, BLACK), ((10.0, 0.0), 2.0, RED)],
+			LineCap::Butt,
+			&[],
+		));
+		assert_eq!(outlines.len(), 1);
+		assert_eq!(outlines[0].rgba, BLACK);
+	}
+
+	#[test]
+	fn dashes_are_outlined_separately() {
+		let outlines = stroke_outlines(&line(LineCap::Butt, &[10.0, 15.0]));
+		assert_eq!(outlines.len(), 4);
+		for (i, outline) in outlines.iter().enumerate() {
+			let start = i as f64 * 25.0;
+			assert!(outline
+				.points
+				.iter()
+				.all(|point| point.0 >= start - 1e-9 && point.0 <= start + 10.0 + 1e-9));
+		}
+		// Dots are dashes of length 0 with round caps.
+		let outlines = stroke_outlines(&line(LineCap::Round, &[0.0, 20.0]));
+		assert_eq!(outlines.len(), 6);
+		for (i, outline) in outlines.iter().enumerate() {
+			let center = center(outline);
+			assert!((center.0 - i as f64 * 20.0).abs() < 1e-9 && center.1.abs() < 1e-9);
+		}
+		assert!(stroke_outlines(&line(LineCap::Butt, &[0.0, 20.0])).is_empty());
+	}
+
+	#[test]
+	fn extent_follows_the_line_width() {
+		let outlines = stroke_outlines(&stroke(
+			&[
+				((0.0, 0.0), 2.0, BLACK),
+				((50.0, 0.0), 6.0, BLACK),
+				((100.0, 0.0), 10.0, BLACK),
+			],
+			LineCap::Butt,
+			&[],
+		));
+		assert_eq!(outlines.len(), 1);
+		let mut widths = outlines[0].points.to_vec();
+		widths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		assert_eq!(
+			widths,
+			vec![
+				(0.0, -1.0),
+				(0.0, 1.0),
+				(50.0, -3.0),
+				(50.0, 3.0),
+				(100.0, -5.0),
+				(100.0, 5.0)
+			]
+		);
+	}
+
+	#[test]
+	fn turns_back_on_itself_without_nan() {
+		for cap in CAPS.iter() {
+			for join in JOINS.iter() {
+				let mut stroke = stroke(
+					&[
+						((0.0, 0.0), 4.0, BLACK),
+						((10.0, 0.0), 4.0, BLACK),
+						((0.0, 0.0), 4.0, BLACK),
+					],
+					*cap,
+					&[],
+				);
+				stroke.style.join = *join;
+				let outlines = stroke_outlines(&stroke);
+				assert_eq!(outlines.len(), 1);
+				assert!(outlines[0]
+					.points
+					.iter()
+					.all(|point| point.0.is_finite() && point.1.is_finite()));
+			}
+		}
+	}
+}
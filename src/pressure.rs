@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+/// Serializable mapping of the pressure of a stylus to the width and opacity of the drawn points.
+///
+/// The pressure, from 0 to 1, is raised to the power of `exponent` first, values above 1 need a firmer touch for the same width.
+/// The lightest touch draws with `minimum_width` times the pen size, full pressure with the whole pen size.
+/// If `maps_opacity` is set, the alpha of the color is scaled from `minimum_opacity` to 1 the same way.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct PressureCurve {
+	pub exponent: f64,
+	pub minimum_width: f64,
+	pub maps_opacity: bool,
+	pub minimum_opacity: f64,
+}
+
+impl Default for PressureCurve {
+	fn default() -> Self {
+		Self {
+			exponent: 1.0,
+			minimum_width: 0.2,
+			maps_opacity: false,
+			minimum_opacity: 0.3,
+		}
+	}
+}
+
+impl PressureCurve {
+	/// Maps the `pressure` of an input event to the width and color of a point drawn with `pen_size` and `rgba`.
+	///
+	/// `pressure` is `None` for devices without a pressure axis, like mice, which draw with `pen_size` and `rgba` unchanged.
+	pub fn apply(&self, pressure: Option<f64>, pen_size: f64, rgba: [f64; 4]) -> (f64, [f64; 4]) {
+		let pressure = match pressure {
+			Some(pressure) if pressure.is_finite() => pressure.clamp(0.0, 1.0),
+			_ => return (pen_size, rgba),
+		};
+		let strength = pressure.powf(self.exponent.max(f64::EPSILON));
+		let width = pen_size * interpolate(self.minimum_width, strength);
+		let mut rgba = rgba;
+		if self.maps_opacity {
+			rgba[3] *= interpolate(self.minimum_opacity, strength);
+		}
+		(width, rgba)
+	}
+}
+
+/// The value at `strength` (0 to 1) between `minimum` and 1.
+fn interpolate(minimum: f64, strength: f64) -> f64 {
+	let minimum = minimum.clamp(0.0, 1.0);
+	minimum + (1.0 - minimum) * strength
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RGBA: [f64; 4] = [0.1, 0.2, 0.3, 0.8];
+
+	fn assert_close(actual: f64, expected: f64) {
+		assert!(
+			(actual - expected).abs() < 1e-9,
+			"{} != {}",
+			actual,
+			expected
+		);
+	}
+
+	#[test]
+	fn missing_pressure_keeps_the_slider_values() {
+		let curves = [
+			PressureCurve::default(),
+			PressureCurve {
+				maps_opacity: true,
+				..PressureCurve::default()
+			},
+		];
+		for curve in curves.iter() {
+			assert_eq!(curve.apply(None, 10.0, RGBA), (10.0, RGBA));
+			assert_eq!(curve.apply(Some(f64::NAN), 10.0, RGBA), (10.0, RGBA));
+		}
+	}
+
+	#[test]
+	fn endpoints_map_to_minimum_and_full_width() {
+		let curve = PressureCurve::default();
+		let (width, rgba) = curve.apply(Some(0.0), 10.0, RGBA);
+		assert_close(width, 2.0);
+		assert_eq!(rgba, RGBA);
+		let (width, rgba) = curve.apply(Some(1.0), 10.0, RGBA);
+		assert_close(width, 10.0);
+		assert_eq!(rgba, RGBA);
+	}
+
+	#[test]
+	fn out_of_range_pressure_is_clamped() {
+		let curve = PressureCurve::default();
+		assert_eq!(
+			curve.apply(Some(-0.5), 10.0, RGBA),
+			curve.apply(Some(0.0), 10.0, RGBA)
+		);
+		assert_eq!(
+			curve.apply(Some(1.5), 10.0, RGBA),
+			curve.apply(Some(1.0), 10.0, RGBA)
+		);
+	}
+
+	#[test]
+	fn default_curve_is_linear() {
+		let (width, rgba) = PressureCurve::default().apply(Some(0.5), 10.0, RGBA);
+		assert_close(width, 6.0);
+		assert_eq!(rgba, RGBA);
+	}
+
+	#[test]
+	fn exponent_needs_a_firmer_touch() {
+		let curve = PressureCurve {
+			exponent: 2.0,
+			minimum_width: 0.0,
+			..PressureCurve::default()
+		};
+		assert_close(curve.apply(Some(0.5), 10.0, RGBA).0, 2.5);
+		assert_close(curve.apply(Some(1.0), 10.0, RGBA).0, 10.0);
+	}
+
+	#[test]
+	fn opacity_is_mapped_when_enabled() {
+		let curve = PressureCurve {
+			maps_opacity: true,
+			minimum_opacity: 0.5,
+			..PressureCurve::default()
+		};
+		let (width, rgba) = curve.apply(Some(0.0), 10.0, RGBA);
+		assert_close(width, 2.0);
+		assert_close(rgba[3], 0.4);
+		assert_eq!(rgba[..3], RGBA[..3]);
+		let (_, rgba) = curve.apply(Some(0.5), 10.0, RGBA);
+		assert_close(rgba[3], 0.6);
+		let (_, rgba) = curve.apply(Some(1.0), 10.0, RGBA);
+		assert_close(rgba[3], 0.8);
+	}
+}
@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+
+/// Usage of the `render` subcommand.
+pub const RENDER_USAGE: &str = "\
+Usage: handwritten-notetaking-editor render <FILE> -o <OUTPUT> [OPTIONS]
+
+Renders the pages of an .hnote file without opening a window.
+
+Options:
+    -o, --output <OUTPUT>   Output file, or output directory if all pages are
+                            rendered as png or svg (named page-<n>.<format>)
+    -p, --page <PAGE>       Only render this page, counted from 1
+    -f, --format <FORMAT>   png, pdf or svg, defaults to the extension of OUTPUT
+    -s, --scale <SCALE>     Scale of the canvas size for png output [default: 1]
+        --size <WxH>        Canvas size [default: 1920x1080]
+    -h, --help              Print this help";
+
+/// Formats that pages can be rendered to.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum OutputFormat {
+	Png,
+	Pdf,
+	Svg,
+}
+
+impl OutputFormat {
+	fn parse(format: &str) -> Option<Self> {
+		match format.to_lowercase().as_str() {
+			"png" => Some(OutputFormat::Png),
+			"pdf" => Some(OutputFormat::Pdf),
+			"svg" => Some(OutputFormat::Svg),
+			_ => None,
+		}
+	}
+
+	/// Whether all pages are written to a single file, otherwise every page gets its own file.
+	pub fn has_pages(self) -> bool {
+		self == OutputFormat::Pdf
+	}
+}
+
+/// Options of the `render` subcommand.
+///
+/// If all pages are rendered to a format without pages, `output` is the directory of the page files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderOptions {
+	pub input: PathBuf,
+	pub output: PathBuf,
+	pub page: Option<usize>,
+	pub format: OutputFormat,
+	pub scale: f64,
+	pub size: (f64, f64),
+}
+
+impl RenderOptions {
+	/// Parses the `arguments` that follow the `render` subcommand.
+	///
+	/// Returns the error message together with `RENDER_USAGE` if they are invalid.
+	pub fn parse(arguments: &[String]) -> Result<Self, String> {
+		let mut input = None;
+		let mut output = None;
+		let mut page = None;
+		let mut format = None;
+		let mut scale = 1.0;
+		let mut size = (1920.0, 1080.0);
+		let mut arguments = arguments.iter();
+		while let Some(argument) = arguments.next() {
+			let mut value = || {
+				arguments
+					.next()
+					.ok_or_else(|| usage_error(&format!("{} requires a value.", argument)))
+			};
+			match argument.as_str() {
+				"-o" | "--output" => output = Some(PathBuf::from(value()?)),
+				"-p" | "--page" => {
+					page = Some(
+						value()?
+							.parse()
+							.map_err(|_| usage_error("The page has to be a number."))?,
+					)
+				}
+				"-f" | "--format" => {
+					format = Some(
+						OutputFormat::parse(value()?)
+							.ok_or_else(|| usage_error("The format has to be png, pdf or svg."))?,
+					)
+				}
+				"-s" | "--scale" => {
+					scale = value()?
+						.parse()
+						.map_err(|_| usage_error("The scale has to be a number."))?
+				}
+				"--size" => {
+					size = parse_size(value()?)
+						.ok_or_else(|| usage_error("The size has to be given as WxH."))?
+				}
+				_ if argument.starts_with('-') => {
+					return Err(usage_error(&format!("Unknown option {}.", argument)))
+				}
+				_ if input.is_none() => input = Some(PathBuf::from(argument)),
+				_ => return Err(usage_error(&format!("Unexpected argument {}.", argument))),
+			}
+		}
+		let input = input.ok_or_else(|| usage_error("The .hnote file is missing."))?;
+		let output = output.ok_or_else(|| usage_error("The output is missing."))?;
+		let format = format
+			.or_else(|| {
+				output
+					.extension()
+					.and_then(|extension| OutputFormat::parse(&extension.to_string_lossy()))
+			})
+			.ok_or_else(|| usage_error("The format could not be derived from the output."))?;
+		// An output like `notes.png` would otherwise become a directory.
+		if page.is_none() && !format.has_pages() && output.extension().is_some() && !output.is_dir()
+		{
+			return Err(usage_error(
+				"Without --page every page is written to its own file, so the output has to be a directory without extension.",
+			));
+		}
+		Ok(Self {
+			input,
+			output,
+			page,
+			format,
+			scale,
+			size,
+		})
+	}
+}
+
+/// Parses a canvas size like `1920x1080`.
+pub fn parse_size(size: &str) -> Option<(f64, f64)> {
+	let mut dimensions = size.split('x').map(str::parse::<f64>);
+	match (dimensions.next(), dimensions.next(), dimensions.next()) {
+		(Some(Ok(width)), Some(Ok(height)), None) => Some((width, height)),
+		_ => None,
+	}
+}
+
+fn usage_error(message: &str) -> String {
+	format!("{}\n\n{}", message, RENDER_USAGE)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(arguments: &str) -> Result<RenderOptions, String> {
+		let arguments: Vec<String> = arguments.split_whitespace().map(String::from).collect();
+		RenderOptions::parse(&arguments)
+	}
+
+	#[test]
+	fn parses_sizes() {
+		assert_eq!(parse_size("1920x1080"), Some((1920.0, 1080.0)));
+		assert_eq!(parse_size("0.5x2"), Some((0.5, 2.0)));
+		assert_eq!(parse_size("1920"), None);
+		assert_eq!(parse_size("1920x1080x3"), None);
+		assert_eq!(parse_size("widexhigh"), None);
+	}
+
+	#[test]
+	fn parses_options() {
+		assert_eq!(
+			parse("notes.hnote --page 2 --format png -s 2 --size 800x600 -o page-2.png"),
+			Ok(RenderOptions {
+				input: PathBuf::from("notes.hnote"),
+				output: PathBuf::from("page-2.png"),
+				page: Some(2),
+				format: OutputFormat::Png,
+				scale: 2.0,
+				size: (800.0, 600.0),
+			})
+		);
+		let options = parse("-o notes.PDF notes.hnote").unwrap();
+		assert_eq!(options.format, OutputFormat::Pdf);
+		assert_eq!(options.page, None);
+		assert_eq!(options.scale, 1.0);
+		assert_eq!(options.size, (1920.0, 1080.0));
+		assert_eq!(
+			parse("notes.hnote -f svg -o pages").unwrap().output,
+			PathBuf::from("pages")
+		);
+	}
+
+	#[test]
+	fn rejects_invalid_options() {
+		for arguments in [
+			"-o out.png",
+			"notes.hnote",
+			"notes.hnote -o out",
+			"notes.hnote -o out.png --page two",
+			"notes.hnote -o out.png --page",
+			"notes.hnote -o out.png --size 10",
+			"notes.hnote -o out.gif",
+			"notes.hnote -o out.png --verbose",
+			"notes.hnote other.hnote -o out.pdf",
+		]
+		.iter()
+		{
+			let error = parse(arguments).unwrap_err();
+			assert!(error.ends_with(RENDER_USAGE), "{}", arguments);
+		}
+	}
+
+	#[test]
+	fn all_pages_need_an_output_directory() {
+		assert!(parse("notes.hnote -o out.png").is_err());
+		assert!(parse("notes.hnote -f svg -o out.svg").is_err());
+		assert!(parse("notes.hnote -f png -o out").is_ok());
+		assert!(parse("notes.hnote -o out.png -p 1").is_ok());
+		assert!(parse("notes.hnote -o out.pdf").is_ok());
+	}
+}
@@ -0,0 +1,371 @@
+use crate::model::{Drawpoint, ElementId, Stroke};
+use std::collections::{HashMap, HashSet};
+
+/// Edge length of the square grid cells in page coordinates.
+const CELL_SIZE: f64 = 64.0;
+
+/// Position of a grid cell, counted in cells from the page origin.
+type Cell = (i64, i64);
+
+fn cell(position: (f64, f64)) -> Cell {
+	(
+		(position.0 / CELL_SIZE).floor() as i64,
+		(position.1 / CELL_SIZE).floor() as i64,
+	)
+}
+
+/// Uniform grid that maps every cell to the strokes with points in it.
+///
+/// The index may name strokes that no longer have points in a cell, but never misses a stroke that has.
+/// `sync()` brings it up to date with the strokes of a layer, only points that were appended are added.
+/// Strokes whose points were changed in any other way have to be reported with `mark_changed()`, they are indexed again.
+/// The index is a cache, so clones start empty and are built on their first `sync()`.
+#[derive(Debug, Default)]
+pub struct SpatialIndex {
+	cells: HashMap<Cell, HashSet<ElementId>>,
+	/// Cells that contain points of each stroke.
+	stroke_cells: HashMap<ElementId, HashSet<Cell>>,
+	/// The indexed strokes in the order of the lines.
+	strokes: Vec<IndexedStroke>,
+	/// Position of each stroke in `strokes`.
+	positions: HashMap<ElementId, usize>,
+	/// Smallest and largest occupied cell, only grows until the index is emptied.
+	bounds: Option<(Cell, Cell)>,
+	/// Strokes that are indexed again on the next `sync()`.
+	changed: HashSet<ElementId>,
+}
+
+impl Clone for SpatialIndex {
+	fn clone(&self) -> Self {
+		Self::default()
+	}
+}
+
+/// Number of points of a stroke that are in the index.
+#[derive(Clone, Copy, Debug)]
+struct IndexedStroke {
+	id: ElementId,
+	len: usize,
+}
+
+impl IndexedStroke {
+	fn new(id: ElementId) -> Self {
+		Self { id, len: 0 }
+	}
+}
+
+impl SpatialIndex {
+	/// Reports that points of the stroke `id` were changed, removed or inserted, so it is indexed again on the next `sync()`.
+	///
+	/// Points that were only appended to a stroke are found without it.
+	pub fn mark_changed(&mut self, id: ElementId) {
+		self.changed.insert(id);
+	}
+
+	/// Updates `self` to contain exactly the points of `lines`.
+	///
+	/// Only the points of the lines are visited that were appended or reported with `mark_changed()`.
+	pub fn sync(&mut self, lines: &[Stroke]) {
+		let same_order = self.strokes.len() == lines.len()
+			&& self
+				.strokes
+				.iter()
+				.zip(lines.iter())
+				.all(|(indexed, line)| indexed.id == line.id);
+		if !same_order {
+			self.reorder(lines);
+		}
+		for (i, line) in lines.iter().enumerate() {
+			let indexed = self.strokes[i];
+			let points = &line.points;
+			let appended = indexed.len <= points.len() && !self.changed.contains(&indexed.id);
+			if appended && indexed.len == points.len() {
+				continue;
+			}
+			let from = if appended {
+				indexed.len
+			} else {
+				self.remove_cells(indexed.id);
+				0
+			};
+			self.insert(i, points, from);
+		}
+		self.changed.clear();
+		if self.strokes.is_empty() {
+			self.bounds = None;
+		}
+	}
+
+	/// Collects the positions in the lines of the strokes that may have points in `rectangle` (`[left, top, right, bottom]`).
+	///
+	/// The positions are in ascending order, `sync()` has to be called before.
+	pub fn query(&self, rectangle: [f64; 4]) -> Vec<usize> {
+		let (min, max) = match self.bounds {
+			Some(bounds) => bounds,
+			None => return Vec::new(),
+		};
+		let from = cell((rectangle[0], rectangle[1]));
+		let to = cell((rectangle[2], rectangle[3]));
+		let mut ids = HashSet::new();
+		for x in from.0.max(min.0)..=to.0.min(max.0) {
+			for y in from.1.max(min.1)..=to.1.min(max.1) {
+				if let Some(strokes) = self.cells.get(&(x, y)) {
+					ids.extend(strokes.iter());
+				}
+			}
+		}
+		let mut positions: Vec<usize> = ids.iter().map(|id| self.positions[id]).collect();
+		positions.sort_unstable();
+		positions
+	}
+
+	/// Checks whether `rectangle` (`[left, top, right, bottom]`) covers every occupied cell.
+	///
+	/// A query with such a rectangle returns all strokes that have points.
+	pub fn covers(&self, rectangle: [f64; 4]) -> bool {
+		match self.bounds {
+			Some((min, max)) => {
+				let from = cell((rectangle[0], rectangle[1]));
+				let to = cell((rectangle[2], rectangle[3]));
+				from.0 <= min.0 && from.1 <= min.1 && to.0 >= max.0 && to.1 >= max.1
+			}
+			None => true,
+		}
+	}
+
+	/// Orders `self.strokes` like `lines`.
+	///
+	/// Strokes that are still part of `lines` keep their cells, new strokes start without points and removed strokes are dropped.
+	/// `self.strokes` is edited in place, so inserting and removing a few lines is cheap.
+	/// A line that moved before other lines is removed and added again.
+	fn reorder(&mut self, lines: &[Stroke]) {
+		for (i, line) in lines.iter().enumerate() {
+			if self.strokes.get(i).map(|indexed| indexed.id) == Some(line.id) {
+				continue;
+			}
+			match self.positions.get(&line.id) {
+				Some(&position) if position > i => self.remove_strokes(i, position),
+				_ => self.insert_stroke(i, line.id),
+			}
+		}
+		self.remove_strokes(lines.len(), self.strokes.len());
+	}
+
+	/// Adds the stroke `id` without points at `position` of `self.strokes`.
+	fn insert_stroke(&mut self, position: usize, id: ElementId) {
+		if position < self.strokes.len() {
+			for other in self.positions.values_mut() {
+				if *other >= position {
+					*other += 1;
+				}
+			}
+		}
+		self.strokes.insert(position, IndexedStroke::new(id));
+		self.positions.insert(id, position);
+	}
+
+	/// Removes the strokes from `position` to `end` (excluded) of `self.strokes`.
+	fn remove_strokes(&mut self, position: usize, end: usize) {
+		if position >= end {
+			return;
+		}
+		let removed: Vec<IndexedStroke> = self.strokes.drain(position..end).collect();
+		for indexed in removed {
+			self.remove_cells(indexed.id);
+			self.stroke_cells.remove(&indexed.id);
+			self.positions.remove(&indexed.id);
+		}
+		if position < self.strokes.len() {
+			for other in self.positions.values_mut() {
+				if *other >= end {
+					*other -= end - position;
+				}
+			}
+		}
+	}
+
+	/// Adds the `points` of the stroke at `position` in the lines from index `from` on.
+	fn insert(&mut self, position: usize, points: &[Drawpoint], from: usize) {
+		let indexed = &mut self.strokes[position];
+		let stroke_cells = self.stroke_cells.entry(indexed.id).or_default();
+		for point in points[from..].iter() {
+			let point_cell = cell(point.position);
+			if stroke_cells.insert(point_cell) {
+				self.cells.entry(point_cell).or_default().insert(indexed.id);
+				self.bounds = Some(match self.bounds {
+					Some((min, max)) => (
+						(min.0.min(point_cell.0), min.1.min(point_cell.1)),
+						(max.0.max(point_cell.0), max.1.max(point_cell.1)),
+					),
+					None => (point_cell, point_cell),
+				});
+			}
+		}
+		indexed.len = points.len();
+	}
+
+	/// Removes the stroke `id` from all cells.
+	fn remove_cells(&mut self, id: ElementId) {
+		let stroke_cells = match self.stroke_cells.get_mut(&id) {
+			Some(stroke_cells) => stroke_cells,
+			None => return,
+		};
+		for stroke_cell in stroke_cells.drain() {
+			if let Some(strokes) = self.cells.get_mut(&stroke_cell) {
+				strokes.remove(&id);
+				if strokes.is_empty() {
+					self.cells.remove(&stroke_cell);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::Layer;
+
+	fn stroke(points: &[(f64, f64)]) -> Stroke {
+		Stroke::new(
+			points
+				.iter()
+				.map(|position| Drawpoint::new(*position, 2.0, [0.0, 0.0, 0.0, 1.0]))
+				.collect(),
+		)
+	}
+
+	/// Positions of the lines with points in `rectangle`, found by scanning every point.
+	fn scanned(lines: &[Stroke], rectangle: [f64; 4]) -> Vec<usize> {
+		(0..lines.len())
+			.filter(|i| {
+				lines[*i].points.iter().any(|point| {
+					let (x, y) = point.position;
+					x >= rectangle[0] && y >= rectangle[1] && x <= rectangle[2] && y <= rectangle[3]
+				})
+			})
+			.collect()
+	}
+
+	/// Checks that `index` finds every line that scanning finds, in a grid of rectangles over the page.
+	fn assert_complete(index: &mut SpatialIndex, lines: &[Stroke]) {
+		index.sync(lines);
+		for x in (-200..1000).step_by(50) {
+			for y in (-200..1000).step_by(50) {
+				let rectangle = [x as f64, y as f64, x as f64 + 30.0, y as f64 + 30.0];
+				let found = index.query(rectangle);
+				for position in scanned(lines, rectangle) {
+					assert!(
+						found.contains(&position),
+						"line {} missed in {:?}",
+						position,
+						rectangle
+					);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn finds_strokes_by_cell() {
+		let lines = vec![
+			stroke(&[(10.0, 10.0), (20.0, 20.0)]),
+			stroke(&[(500.0, 500.0)]),
+			stroke(&[(-100.0, 10.0), (600.0, 10.0)]),
+		];
+		let mut index = SpatialIndex::default();
+		index.sync(&lines);
+		assert_eq!(index.query([0.0, 0.0, 30.0, 30.0]), vec![0]);
+		assert_eq!(index.query([490.0, 490.0, 510.0, 510.0]), vec![1]);
+		assert_eq!(index.query([590.0, 0.0, 610.0, 20.0]), vec![2]);
+		assert!(index.query([200.0, 200.0, 210.0, 210.0]).is_empty());
+		assert!(index.covers([-100.0, 0.0, 600.0, 500.0]));
+		assert!(!index.covers([0.0, 0.0, 600.0, 500.0]));
+		assert_complete(&mut index, &lines);
+	}
+
+	#[test]
+	fn follows_appended_points_and_changed_lines() {
+		let mut lines = vec![stroke(&[(10.0, 10.0)]), stroke(&[(300.0, 300.0)])];
+		let mut index = SpatialIndex::default();
+		assert_complete(&mut index, &lines);
+
+		lines[0]
+			.points
+			.push(Drawpoint::new((700.0, 700.0), 2.0, [0.0; 4]));
+		assert_complete(&mut index, &lines);
+		assert_eq!(index.query([690.0, 690.0, 710.0, 710.0]), vec![0]);
+
+		lines.insert(1, stroke(&[(400.0, 100.0)]));
+		lines.remove(0);
+		assert_complete(&mut index, &lines);
+		assert_eq!(index.query([390.0, 90.0, 410.0, 110.0]), vec![0]);
+		assert_eq!(index.query([290.0, 290.0, 310.0, 310.0]), vec![1]);
+
+		let last = lines.pop().unwrap();
+		lines.insert(0, last);
+		assert_complete(&mut index, &lines);
+		assert_eq!(index.query([290.0, 290.0, 310.0, 310.0]), vec![0]);
+
+		lines.clear();
+		index.sync(&lines);
+		assert!(index.query([-1000.0, -1000.0, 1000.0, 1000.0]).is_empty());
+	}
+
+	#[test]
+	fn follows_moved_middle_points_and_replaced_strokes() {
+		let mut lines = vec![stroke(&[(10.0, 10.0), (300.0, 300.0), (20.0, 20.0)])];
+		let mut index = SpatialIndex::default();
+		assert_complete(&mut index, &lines);
+
+		lines[0].points[1].position = (600.0, 600.0);
+		index.mark_changed(lines[0].id);
+		assert_complete(&mut index, &lines);
+		assert!(index.query([290.0, 290.0, 310.0, 310.0]).is_empty());
+		assert_eq!(index.query([590.0, 590.0, 610.0, 610.0]), vec![0]);
+
+		let id = lines[0].id;
+		lines[0] = stroke(&[(10.0, 10.0), (100.0, 800.0), (20.0, 20.0)]);
+		lines[0].id = id;
+		index.mark_changed(id);
+		assert_complete(&mut index, &lines);
+		assert!(index.query([590.0, 590.0, 610.0, 610.0]).is_empty());
+		assert_eq!(index.query([90.0, 790.0, 110.0, 810.0]), vec![0]);
+	}
+
+	#[test]
+	fn layers_report_changed_lines() {
+		let mut layer = Layer::new("Layer 1");
+		layer.push_line(stroke(&[(10.0, 10.0), (300.0, 300.0), (20.0, 20.0)]));
+		assert_eq!(
+			layer.lines_in_rectangle([290.0, 290.0, 310.0, 310.0]),
+			vec![0]
+		);
+
+		layer.line_mut(0).points[1].position = (600.0, 600.0);
+		assert!(layer
+			.lines_in_rectangle([290.0, 290.0, 310.0, 310.0])
+			.is_empty());
+		assert_eq!(
+			layer.lines_in_rectangle([590.0, 590.0, 610.0, 610.0]),
+			vec![0]
+		);
+
+		let mut replacement = stroke(&[(10.0, 10.0), (100.0, 800.0), (20.0, 20.0)]);
+		replacement.id = layer.lines()[0].id;
+		layer.splice_lines(0..1, vec![replacement]);
+		assert!(layer
+			.lines_in_rectangle([590.0, 590.0, 610.0, 610.0])
+			.is_empty());
+		assert_eq!(
+			layer.lines_in_rectangle([90.0, 790.0, 110.0, 810.0]),
+			vec![0]
+		);
+
+		layer.push_point(Drawpoint::new((900.0, 100.0), 2.0, [0.0; 4]));
+		assert_eq!(
+			layer.lines_in_rectangle([890.0, 90.0, 910.0, 110.0]),
+			vec![0]
+		);
+	}
+}
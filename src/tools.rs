@@ -0,0 +1,903 @@
+use crate::history::Operation;
+use crate::model::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Enum representation of possible tools that a user can directly apply to the canvas.
+///
+/// Front ends select the active tool with it and match it to the corresponding `DrawTool`.
+/// Strokes store the value of the tool that drew them.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub enum CurrentDrawTool {
+	Pencil,
+	Eraser,
+	LineEraser,
+	LineTool,
+	Drag,
+	RectangleSelection,
+	Clear,
+}
+
+/// Trait for a tool that can directly manipulate the canvas.
+///
+/// All implementations have to get a similarly named enum value in `CurrentDrawTool`.
+pub trait DrawTool {
+	/// This function is called in every time step that the user interacts with the canvas.
+	///
+	/// Lines and images of `layer` can be manipulated depending on the `position` input.
+	/// `layer` is the active layer of a page, hidden and locked layers are never passed to tools.
+	/// `image_sizes` contains the displayed width and height of every image in `layer.images`.
+	/// Returns whether lines or images of `layer` were changed, front ends only redraw the page when they were.
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		pen_size: f64,
+		pen_is_active: bool,
+		rgba: [f64; 4],
+	) -> bool;
+
+	/// Called when the pen is pressed on `layer`, before `manipulate()` is called with the active pen.
+	///
+	/// `stroke` is an empty stroke with the current tool and line pattern, tools that draw lines add it to `layer`.
+	fn start(&mut self, _layer: &mut Layer, _stroke: Stroke) {}
+
+	/// Called when the pen is released, `layer` is the layer at `layer_index` of the page at `page` that it was pressed on.
+	///
+	/// Returns the operation that records what the tool changed since `start()`, `None` if nothing changed.
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation>;
+
+	/// Misc function for finding the `closest_position` in all `lines` of `layer`.
+	///
+	/// Only the points around `position` are searched, see `Layer::closest_line_position()`.
+	fn closest_line_position(layer: &mut Layer, position: (f64, f64)) -> Option<(usize, (f64, f64))>
+	where
+		Self: Sized,
+	{
+		layer.closest_line_position(position)
+	}
+}
+
+/// Adds the line that a drawing tool starts to `layer` and returns its id.
+fn start_stroke(layer: &mut Layer, stroke: Stroke) -> ElementId {
+	let id = stroke.id;
+	layer.push_line(stroke);
+	id
+}
+
+/// Records the line with the id `stroke` that a drawing tool added to `layer`, the layer at `layer_index` of the page at `page`.
+///
+/// A line without points is removed from `layer` again instead.
+fn finish_stroke(
+	stroke: Option<ElementId>,
+	page: usize,
+	layer_index: usize,
+	layer: &mut Layer,
+) -> Option<Operation> {
+	let index = layer.line_index(stroke?)?;
+	if layer.lines()[index].points.is_empty() {
+		layer.splice_lines(index..index + 1, Vec::new());
+		return None;
+	}
+	Some(Operation::AddStroke {
+		page,
+		layer: layer_index,
+		index,
+		stroke: layer.lines()[index].clone(),
+	})
+}
+
+/// Lines that a tool replaced since the pen was pressed, as replacements of ranges of the lines of a layer.
+///
+/// Replacements of ranges that overlap or touch are merged, so a line that is erased step by step is only stored once.
+#[derive(Clone, Debug, Default)]
+struct LineReplacements {
+	/// Index of the first replacing line, the replaced lines and the number of replacing lines, ordered by index.
+	ranges: Vec<(usize, Vec<Stroke>, usize)>,
+}
+
+impl LineReplacements {
+	/// Replaces the lines in `range` of `layer` with `inserted` and remembers the replaced lines.
+	fn splice(&mut self, layer: &mut Layer, range: Range<usize>, inserted: Vec<Stroke>) {
+		let inserted_count = inserted.len();
+		let first = self
+			.ranges
+			.iter()
+			.position(|(index, _, count)| index + count >= range.start)
+			.unwrap_or(self.ranges.len());
+		let last = first
+			+ self.ranges[first..]
+				.iter()
+				.take_while(|(index, _, _)| *index <= range.end)
+				.count();
+		let merged: Vec<_> = self.ranges.drain(first..last).collect();
+		let start = merged
+			.first()
+			.map_or(range.start, |(index, _, _)| range.start.min(*index));
+		let end = merged
+			.last()
+			.map_or(range.end, |(index, _, count)| range.end.max(index + count));
+		// Lines outside of the merged ranges have not been replaced yet.
+		let mut removed = Vec::new();
+		let mut position = start;
+		for (index, lines, count) in merged {
+			removed.extend_from_slice(&layer.lines()[position..index]);
+			removed.extend(lines);
+			position = index + count;
+		}
+		removed.extend_from_slice(&layer.lines()[position..end]);
+		layer.splice_lines(range.clone(), inserted);
+		for (index, _, _) in self.ranges[first..].iter_mut() {
+			*index = *index + inserted_count - range.len();
+		}
+		let count = end - start - range.len() + inserted_count;
+		self.ranges.insert(first, (start, removed, count));
+	}
+
+	/// Describes the replacements in `layer`, the layer at `layer_index` of the page at `page`, as operation and forgets them.
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &Layer) -> Option<Operation> {
+		let mut operations: Vec<Operation> = self
+			.ranges
+			.drain(..)
+			.filter_map(|(index, removed, count)| {
+				let inserted = layer.lines().get(index..index + count)?;
+				Operation::replace_lines(page, layer_index, index, &removed, inserted)
+			})
+			.collect();
+		match operations.len() {
+			0 => None,
+			1 => operations.pop(),
+			_ => Some(Operation::Group(operations)),
+		}
+	}
+}
+
+/// Basic `DrawTool` to create lines.
+#[derive(Clone, Debug, Default)]
+pub struct Pencil {
+	/// Id of the line that is drawn while the pen is pressed.
+	stroke: Option<ElementId>,
+}
+
+impl DrawTool for Pencil {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		pen_size: f64,
+		pen_is_active: bool,
+		rgba: [f64; 4],
+	) -> bool {
+		if pen_is_active {
+			layer.push_point(Drawpoint::new(position, pen_size, rgba));
+		}
+		pen_is_active
+	}
+
+	fn start(&mut self, layer: &mut Layer, stroke: Stroke) {
+		self.stroke = Some(start_stroke(layer, stroke));
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation> {
+		finish_stroke(self.stroke.take(), page, layer_index, layer)
+	}
+}
+
+/// Basic `DrawTool` to erase Drawpoints in lines and split where the `Drawpoint` was deleted.
+///
+/// The first part of a split line keeps its id, the other parts get new ids and the properties of the line.
+#[derive(Clone, Debug, Default)]
+pub struct Eraser {
+	replacements: LineReplacements,
+}
+
+impl DrawTool for Eraser {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		pen_size: f64,
+		pen_is_active: bool,
+		_rgba: [f64; 4],
+	) -> bool {
+		let mut new_element_count = 0;
+		if pen_is_active {
+			let candidates = layer.lines_near(position, pen_size);
+			let mut removal_queue: Vec<(usize, usize)> = Vec::new();
+			for i in candidates {
+				for (j, point) in layer.lines()[i].points.iter().enumerate() {
+					let distance = ((point.position.0 - position.0).powf(2.0)
+						+ (point.position.1 - position.1).powf(2.0))
+					.sqrt();
+					if distance < pen_size {
+						removal_queue.push((i, j));
+					};
+				}
+			}
+			for indices in removal_queue {
+				let i = indices.0 + new_element_count;
+				let j = indices.1;
+				if i < layer.lines().len() && j < layer.lines()[i].points.len() {
+					let mut line = layer.lines()[i].clone();
+					let split = line.split_off(j);
+					self.replacements.splice(layer, i..i + 1, vec![line, split]);
+					new_element_count += 1;
+				}
+			}
+		}
+		new_element_count > 0
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation> {
+		self.replacements.finish(page, layer_index, layer)
+	}
+}
+
+/// Erases the whole `line` on contact with the tool.
+#[derive(Clone, Debug, Default)]
+pub struct LineEraser {
+	replacements: LineReplacements,
+}
+
+impl DrawTool for LineEraser {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		pen_size: f64,
+		pen_is_active: bool,
+		_rgba: [f64; 4],
+	) -> bool {
+		if !pen_is_active {
+			return false;
+		}
+		let erased: Vec<usize> = layer
+			.lines_near(position, pen_size)
+			.into_iter()
+			.filter(|i| {
+				layer.lines()[*i].points.iter().any(|point| {
+					((point.position.0 - position.0).powf(2.0)
+						+ (point.position.1 - position.1).powf(2.0))
+					.sqrt() < pen_size
+				})
+			})
+			.collect();
+		let changed = !erased.is_empty();
+		for i in erased.into_iter().rev() {
+			self.replacements.splice(layer, i..i + 1, Vec::new());
+		}
+		changed
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation> {
+		self.replacements.finish(page, layer_index, layer)
+	}
+}
+
+/// Draws straight lines from the drag `starting_position` to the pointer `position`.
+///
+/// As many Drawpoints are inserted depending on how long the line is.
+#[derive(Clone, Debug, Default)]
+pub struct LineTool {
+	/// Id of the line that is drawn while the pen is pressed.
+	stroke: Option<ElementId>,
+}
+
+impl DrawTool for LineTool {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		pen_size: f64,
+		pen_is_active: bool,
+		rgba: [f64; 4],
+	) -> bool {
+		let line_index = match self.stroke.and_then(|id| layer.line_index(id)) {
+			Some(line_index) if pen_is_active => line_index,
+			_ => return false,
+		};
+		let starting_point = match layer.lines()[line_index].points.first() {
+			Some(point) => point.clone(),
+			None => Drawpoint::new(position, pen_size, rgba),
+		};
+		let distance = (starting_point.position.0 - position.0).powf(2.0)
+			+ (starting_point.position.1 - position.1).powf(2.0);
+		let point_count = distance / 10000.0;
+		let vector = (
+			(position.0 - starting_point.position.0) / point_count,
+			(position.1 - starting_point.position.1) / point_count,
+		);
+		let lines = &mut layer.line_mut(line_index).points;
+		lines.clear();
+		lines.push(starting_point.clone());
+		for i in 1..point_count as usize {
+			let new_position = (
+				starting_point.position.0 + vector.0 * (i as f64),
+				starting_point.position.1 + vector.1 * (i as f64),
+			);
+			lines.push(Drawpoint::new(new_position, pen_size, rgba));
+		}
+		true
+	}
+
+	fn start(&mut self, layer: &mut Layer, stroke: Stroke) {
+		self.stroke = Some(start_stroke(layer, stroke));
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation> {
+		finish_stroke(self.stroke.take(), page, layer_index, layer)
+	}
+}
+
+/// Enum representation of possible `Drag` tool modes.
+#[derive(Clone, Debug)]
+pub enum DragMode {
+	Line,
+	Image,
+	None,
+}
+
+/// `Drag` tool for draging the closest line/image on at a time.
+///
+/// Previous values have to be saved before translating the positions for correct calculations.
+/// The dragged line or image is tracked by its id, so it stays selected when other elements are added or removed.
+#[derive(Clone, Debug)]
+pub struct Drag {
+	selection: Option<ElementId>,
+	starting_position: (f64, f64),
+	previous_pen_is_active: bool,
+	previous_points: Vec<Drawpoint>,
+	previous_image_position: (f64, f64),
+	/// Position of the selected image when the pen was pressed.
+	starting_image_position: (f64, f64),
+	/// Vector that the selection was moved by since the pen was pressed.
+	translation: Option<(f64, f64)>,
+	mode: DragMode,
+}
+
+impl Default for Drag {
+	fn default() -> Self {
+		Self {
+			selection: None,
+			starting_position: (0.0, 0.0),
+			previous_pen_is_active: false,
+			previous_points: Vec::<Drawpoint>::new(),
+			previous_image_position: (0.0, 0.0),
+			starting_image_position: (0.0, 0.0),
+			translation: None,
+			mode: DragMode::None,
+		}
+	}
+}
+
+impl Drag {
+	/// Misc function for finding the `closest_position` in all `images` of `layer`.
+	///
+	/// `image_sizes` contains the displayed width and height of every image.
+	pub fn closest_image(
+		layer: &Layer,
+		image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+	) -> Option<(usize, (f64, f64))> {
+		let mut lowest_distance = f64::INFINITY;
+		let mut image_index = None;
+		let mut closest_point = None;
+		for (i, (image, size)) in layer.images.iter().zip(image_sizes).enumerate() {
+			let handle_position = Self::get_handle_position(image, *size);
+			let distance = ((handle_position.0 - position.0).powf(2.0)
+				+ (handle_position.1 - position.1).powf(2.0))
+			.sqrt();
+			if distance < lowest_distance {
+				lowest_distance = distance;
+				image_index = Some(i);
+				closest_point = Some(handle_position);
+			};
+		}
+		let image_index = image_index?;
+		let closest_point = closest_point?;
+		Some((image_index, closest_point))
+	}
+
+	fn get_handle_position(image: &Image, size: (f64, f64)) -> (f64, f64) {
+		(
+			image.position.0 + (size.0 - image.position.0) / 2.0,
+			image.position.1 + (size.1 - image.position.1) / 2.0,
+		)
+	}
+
+	/// Calculates and sets `DragMode` for `self`, depending on pointer `position`.
+	fn set_mode(&mut self, layer: &mut Layer, image_sizes: &[(f64, f64)], position: (f64, f64)) {
+		self.mode = match Self::closest_line_position(layer, position) {
+			Some((_, closest_line_position)) => {
+				match Self::closest_image(layer, image_sizes, position) {
+					Some((_, closest_image_position)) => {
+						let line_distance = (closest_line_position.0 - position.0).powf(2.0)
+							+ (closest_line_position.1 - position.1).powf(2.0);
+						let image_distance = (closest_image_position.0 - position.0).powf(2.0)
+							+ (closest_image_position.1 - position.1).powf(2.0);
+						if line_distance <= image_distance {
+							DragMode::Line
+						} else {
+							DragMode::Image
+						}
+					}
+					None => DragMode::Line,
+				}
+			}
+			None => match Self::closest_image(layer, image_sizes, position) {
+				Some(_) => DragMode::Image,
+				None => DragMode::None,
+			},
+		}
+	}
+
+	/// Translates the positions of the selected line depending on drag `vector`.
+	///
+	/// Returns whether a line was selected.
+	fn line_drag(&mut self, layer: &mut Layer, vector: (f64, f64)) -> bool {
+		let line_index = match self.selection.and_then(|id| layer.line_index(id)) {
+			Some(line_index) => line_index,
+			None => return false,
+		};
+		let line = layer.line_mut(line_index);
+		if !self.previous_pen_is_active {
+			self.previous_points = line.points.clone();
+		}
+		for (point, prev_point) in line.points.iter_mut().zip(self.previous_points.iter()) {
+			point.position.0 = prev_point.position.0 + vector.0;
+			point.position.1 = prev_point.position.1 + vector.1;
+		}
+		self.translation = Some(vector);
+		true
+	}
+
+	/// Translates the position of the selected image depending on drag `vector`.
+	///
+	/// Returns whether an image was selected.
+	fn image_drag(
+		&mut self,
+		layer: &mut Layer,
+		image_sizes: &[(f64, f64)],
+		closest_image_position: (f64, f64),
+		vector: (f64, f64),
+	) -> bool {
+		let image_index = match self.selection.and_then(|id| layer.image_index(id)) {
+			Some(image_index) => image_index,
+			None => return false,
+		};
+		let (width, height) = image_sizes[image_index];
+		let image = &mut layer.images[image_index];
+		if !self.previous_pen_is_active {
+			self.previous_image_position = closest_image_position;
+			self.starting_image_position = image.position;
+		}
+		image.position.0 = self.previous_image_position.0 + vector.0 - width / 2.0;
+		image.position.1 = self.previous_image_position.1 + vector.1 - height / 2.0;
+		self.translation = Some((
+			image.position.0 - self.starting_image_position.0,
+			image.position.1 - self.starting_image_position.1,
+		));
+		true
+	}
+}
+
+impl DrawTool for Drag {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		_pen_size: f64,
+		pen_is_active: bool,
+		_rgba: [f64; 4],
+	) -> bool {
+		let mut changed = false;
+		if pen_is_active {
+			if !self.previous_pen_is_active {
+				self.set_mode(layer, image_sizes, position);
+				self.starting_position = position;
+			}
+			let vector = (
+				position.0 - self.starting_position.0,
+				position.1 - self.starting_position.1,
+			);
+			changed = match self.mode {
+				DragMode::Line => {
+					if !self.previous_pen_is_active {
+						let line_index = Self::closest_line_position(layer, position).unwrap().0;
+						self.selection = Some(layer.lines()[line_index].id);
+					}
+					self.line_drag(layer, vector)
+				}
+				DragMode::Image => {
+					let (image_index, closest_image_position) =
+						Self::closest_image(layer, image_sizes, position).unwrap();
+					if !self.previous_pen_is_active {
+						self.selection = Some(layer.images[image_index].id);
+					}
+					self.image_drag(layer, image_sizes, closest_image_position, vector)
+				}
+				DragMode::None => false,
+			};
+		}
+		self.previous_pen_is_active = pen_is_active;
+		changed
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, _layer: &mut Layer) -> Option<Operation> {
+		self.previous_pen_is_active = false;
+		let vector = self
+			.translation
+			.take()
+			.filter(|vector| *vector != (0.0, 0.0))?;
+		let selection = vec![self.selection?];
+		let (lines, images) = match self.mode {
+			DragMode::Line => (selection, Vec::new()),
+			DragMode::Image => (Vec::new(), selection),
+			DragMode::None => return None,
+		};
+		Some(Operation::MoveElements {
+			page,
+			layer: layer_index,
+			lines,
+			images,
+			vector,
+		})
+	}
+}
+
+/// Enum representation of possible `RectangleSelection` tool modes.
+#[derive(Clone, Debug)]
+enum RectangleSelectionMode {
+	Selection,
+	Translation,
+}
+
+/// Line elements can be selected by grouping them in a rectangle and then be repositioned.
+///
+/// Previous values have to be saved before translating the positions for correct calculations.
+/// The selected lines are stored by their id together with their points before the translation.
+/// Front ends draw `rectangle()` as an overlay while the tool is active.
+#[derive(Clone, Debug)]
+pub struct RectangleSelection {
+	rectangle: [f64; 4],
+	previous_rectangle: [f64; 4],
+	selection: HashMap<ElementId, Vec<Drawpoint>>,
+	starting_position: (f64, f64),
+	previous_pen_is_active: bool,
+	/// Vector that the selected lines were moved by since the pen was pressed.
+	translation: Option<(f64, f64)>,
+	mode: RectangleSelectionMode,
+}
+
+impl Default for RectangleSelection {
+	fn default() -> Self {
+		Self {
+			rectangle: [0.0; 4],
+			previous_rectangle: [0.0; 4],
+			selection: HashMap::<ElementId, Vec<Drawpoint>>::new(),
+			starting_position: (0.0, 0.0),
+			previous_pen_is_active: false,
+			translation: None,
+			mode: RectangleSelectionMode::Selection,
+		}
+	}
+}
+
+impl RectangleSelection {
+	/// Current selection rectangle as `[left, top, right, bottom]`.
+	pub fn rectangle(&self) -> [f64; 4] {
+		self.rectangle
+	}
+
+	/// Removes the selection rectangle.
+	pub fn reset(&mut self) {
+		self.rectangle = [0.0; 4];
+	}
+
+	/// Checks if a `position` is in `self.rectangle`.
+	///
+	/// Edge cases are excluded.
+	fn is_in_rectangle(&self, position: (f64, f64)) -> bool {
+		let rectangle = self.rectangle;
+		position.0 > rectangle[0]
+			&& position.1 > rectangle[1]
+			&& position.0 < rectangle[2]
+			&& position.1 < rectangle[3]
+	}
+
+	/// Updates `self.selection` set depending on whether or not one of the `Drawpoint` positions is in `self.rectangle`.
+	///
+	/// Only the lines of `layer` around `self.rectangle` are checked.
+	fn update_selection(&mut self, layer: &mut Layer) {
+		for i in layer.lines_in_rectangle(self.rectangle) {
+			let line = &layer.lines()[i];
+			if line
+				.points
+				.iter()
+				.any(|point| self.is_in_rectangle(point.position))
+			{
+				self.selection.insert(line.id, line.points.clone());
+			}
+		}
+	}
+
+	/// Updates `self.rectangle` depending on pointer `position` and `self.starting_position`.
+	fn update_rectangle(&mut self, position: (f64, f64)) {
+		let rectangle = &mut self.rectangle;
+		if self.starting_position.0 < position.0 {
+			rectangle[0] = self.starting_position.0;
+			rectangle[2] = position.0;
+		} else {
+			rectangle[0] = position.0;
+			rectangle[2] = self.starting_position.0;
+		}
+		if self.starting_position.1 < position.1 {
+			rectangle[1] = self.starting_position.1;
+			rectangle[3] = position.1;
+		} else {
+			rectangle[1] = position.1;
+			rectangle[3] = self.starting_position.1;
+		}
+	}
+
+	/// Translates the positions of the selected lines of `layer` depending on the drag `vector`.
+	fn translate_positions(&mut self, layer: &mut Layer, position: (f64, f64)) {
+		let vector = (
+			position.0 - self.starting_position.0,
+			position.1 - self.starting_position.1,
+		);
+		self.rectangle[0] = self.previous_rectangle[0] + vector.0;
+		self.rectangle[1] = self.previous_rectangle[1] + vector.1;
+		self.rectangle[2] = self.previous_rectangle[2] + vector.0;
+		self.rectangle[3] = self.previous_rectangle[3] + vector.1;
+		for i in 0..layer.lines().len() {
+			let previous_line = match self.selection.get(&layer.lines()[i].id) {
+				Some(previous_line) => previous_line,
+				None => continue,
+			};
+			for (point, prev_point) in layer.line_mut(i).points.iter_mut().zip(previous_line) {
+				point.position.0 = prev_point.position.0 + vector.0;
+				point.position.1 = prev_point.position.1 + vector.1;
+			}
+		}
+		self.translation = Some(vector);
+	}
+
+	/// Calculates and sets `RectangleSelectionMode` for `self`, depending on if `self.starting_position` is in `self.rectangle`.
+	fn set_mode(&mut self) {
+		if self.is_in_rectangle(self.starting_position) {
+			self.mode = RectangleSelectionMode::Translation;
+		} else {
+			self.mode = RectangleSelectionMode::Selection;
+		}
+	}
+}
+
+impl DrawTool for RectangleSelection {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		position: (f64, f64),
+		_pen_size: f64,
+		pen_is_active: bool,
+		_rgba: [f64; 4],
+	) -> bool {
+		let mut changed = false;
+		if pen_is_active {
+			if !self.previous_pen_is_active {
+				self.starting_position = position;
+				self.previous_rectangle = self.rectangle;
+				self.selection.clear();
+				self.set_mode();
+				self.update_selection(layer);
+			}
+			match self.mode {
+				RectangleSelectionMode::Translation => {
+					self.translate_positions(layer, position);
+					changed = !self.selection.is_empty();
+				}
+				RectangleSelectionMode::Selection => self.update_rectangle(position),
+			}
+		}
+		self.previous_pen_is_active = pen_is_active;
+		changed
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, _layer: &mut Layer) -> Option<Operation> {
+		self.previous_pen_is_active = false;
+		let vector = self
+			.translation
+			.take()
+			.filter(|vector| *vector != (0.0, 0.0))?;
+		let mut lines: Vec<ElementId> = self.selection.keys().copied().collect();
+		if lines.is_empty() {
+			return None;
+		}
+		lines.sort_unstable();
+		Some(Operation::MoveElements {
+			page,
+			layer: layer_index,
+			lines,
+			images: Vec::new(),
+			vector,
+		})
+	}
+}
+
+/// Removes all `lines` of `layer` while the pen is active.
+///
+/// Images are excluded.
+#[derive(Clone, Debug, Default)]
+pub struct Clear {
+	replacements: LineReplacements,
+}
+
+impl DrawTool for Clear {
+	fn manipulate(
+		&mut self,
+		layer: &mut Layer,
+		_image_sizes: &[(f64, f64)],
+		_position: (f64, f64),
+		_pen_size: f64,
+		pen_is_active: bool,
+		_rgba: [f64; 4],
+	) -> bool {
+		if !pen_is_active || layer.lines().is_empty() {
+			return false;
+		}
+		self.replacements
+			.splice(layer, 0..layer.lines().len(), Vec::new());
+		true
+	}
+
+	fn finish(&mut self, page: usize, layer_index: usize, layer: &mut Layer) -> Option<Operation> {
+		self.replacements.finish(page, layer_index, layer)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BLACK: [f64; 4] = [0.0, 0.0, 0.0, 1.0];
+
+	/// Page whose only layer has a horizontal line of 10 points at every y in `rows`.
+	fn page(rows: &[f64]) -> Page {
+		let mut page = Page::new();
+		for y in rows {
+			page.layers[0].push_line(Stroke::new(
+				(0..10)
+					.map(|x| Drawpoint::new((x as f64 * 10.0, *y), 2.0, BLACK))
+					.collect(),
+			));
+		}
+		page
+	}
+
+	/// Presses the pen on `page`, moves it along `path` with `tool` and releases it.
+	///
+	/// Checks that the operation of `tool` turns the page before into the page after and back.
+	fn assert_recorded(
+		tool: &mut dyn DrawTool,
+		page: Page,
+		path: &[(f64, f64)],
+	) -> Option<Operation> {
+		let mut pages = vec![page];
+		let before = serde_json::to_value(&pages).unwrap();
+		let layer = &mut pages[0].layers[0];
+		tool.start(layer, Stroke::new(Vec::new()));
+		for position in path {
+			tool.manipulate(layer, &[], *position, 3.0, true, BLACK);
+		}
+		let operation = tool.finish(0, 0, layer);
+		let after = serde_json::to_value(&pages).unwrap();
+		match &operation {
+			Some(operation) => {
+				operation.revert(&mut pages);
+				assert_eq!(serde_json::to_value(&pages).unwrap(), before);
+				operation.apply(&mut pages);
+				assert_eq!(serde_json::to_value(&pages).unwrap(), after);
+			}
+			None => assert_eq!(after, before),
+		}
+		operation
+	}
+
+	#[test]
+	fn drawing_tools_record_their_stroke() {
+		let operation = assert_recorded(
+			&mut Pencil::default(),
+			page(&[0.0]),
+			&[(5.0, 5.0), (6.0, 6.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::AddStroke { index: 1, ref stroke, .. }) if stroke.points.len() == 2
+		));
+		assert!(assert_recorded(&mut Pencil::default(), page(&[0.0]), &[]).is_none());
+		let operation = assert_recorded(
+			&mut LineTool::default(),
+			page(&[0.0]),
+			&[(0.0, 0.0), (300.0, 400.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::AddStroke { index: 1, ref stroke, .. }) if stroke.points.len() == 25
+		));
+	}
+
+	#[test]
+	fn erasers_record_the_replaced_lines() {
+		// The first line is erased several times, its replacements are merged into one.
+		let path = [
+			(10.0, 0.0),
+			(20.0, 0.0),
+			(20.0, 40.0),
+			(50.0, 40.0),
+			(70.0, 0.0),
+		];
+		let operation = assert_recorded(&mut Eraser::default(), page(&[0.0, 20.0, 40.0]), &path);
+		assert!(matches!(
+			operation,
+			Some(Operation::Group(ref operations)) if operations.len() == 2
+		));
+		let operation = assert_recorded(
+			&mut LineEraser::default(),
+			page(&[0.0, 20.0, 40.0]),
+			&[(10.0, 0.0), (10.0, 40.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::Group(ref operations)) if operations.len() == 2
+		));
+		let operation = assert_recorded(
+			&mut Clear::default(),
+			page(&[0.0, 20.0]),
+			&[(0.0, 0.0), (0.0, 0.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::ReplaceLines { index: 0, ref removed, ref inserted, .. })
+				if removed.len() == 2 && inserted.is_empty()
+		));
+	}
+
+	#[test]
+	fn moving_tools_record_the_moved_lines() {
+		let page = page(&[0.0, 20.0]);
+		let id = page.layers[0].lines()[1].id;
+		let operation = assert_recorded(
+			&mut Drag::default(),
+			page.clone(),
+			&[(30.0, 21.0), (35.0, 31.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::MoveElements { ref lines, vector, .. }) if *lines == [id] && vector == (5.0, 10.0)
+		));
+		let mut rectangle_selection = RectangleSelection::default();
+		let selected = assert_recorded(
+			&mut rectangle_selection,
+			page.clone(),
+			&[(-5.0, 10.0), (100.0, 30.0)],
+		);
+		assert!(selected.is_none());
+		let operation = assert_recorded(
+			&mut rectangle_selection,
+			page,
+			&[(50.0, 20.0), (50.0, 25.0)],
+		);
+		assert!(matches!(
+			operation,
+			Some(Operation::MoveElements { ref lines, vector, .. }) if *lines == [id] && vector == (0.0, 5.0)
+		));
+	}
+}